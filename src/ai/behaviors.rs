@@ -0,0 +1,415 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+
+use super::{
+    maneuvers::{Pilot, MIN_DELTA_V},
+    orientation_controller::MIN_ROTATION_THETA,
+    pursuit::PursuitBurn,
+    AGGRO_RANGE,
+};
+
+const MATCH_DELTA_V_THRESHOLD: f32 = 3000.0;
+const AVOID_CRASH_TIME_HORIZON_S: f32 = 8.0;
+// A player closing in to pass closer than this within the horizon is a ram to dodge.
+const EVADE_TIME_HORIZON_S: f32 = 2.0;
+const EVADE_MAX_GAP: f32 = 150.0;
+const CIRCLE_RADIUS: f32 = AGGRO_RANGE * 0.75;
+const CIRCLE_SPEED: f32 = 300.0;
+// Kamikazes close in at this speed, no braking.
+const RAM_SPEED: f32 = 1500.0;
+// Ships that keep away start backing off inside this fraction of their range, and flee inside the second one.
+const KEEP_AWAY_FRACTIONS: (f32, f32) = (0.5, 0.3);
+// Added to the current behavior's utility, so that ships don't dither between two close scores.
+const INERTIA: f32 = 0.1;
+
+// What a ship knows when choosing and flying a behavior.
+pub struct Situation {
+    pub to_player: Vec2,
+    pub relative_velocity: Vec2, // the player's, relative to ours
+    pub aim: Vec2,               // where to point our lasers, relative to us
+    pub velocity: Vec2,
+    pub gravity: Vec2,               // the last acceleration we felt
+    pub time_to_impact: Option<f32>, // with a celestial body, on our current course
+    pub player_landed: bool,
+    pub pursuit: Option<PursuitBurn>, // our latest pursuit plan, if still fresh
+}
+
+impl Situation {
+    // When and how close the player will pass by, if it comes within `horizon` seconds.
+    fn closest_approach(&self, horizon: f32) -> Option<(f32, Vec2)> {
+        let speed_squared = self.relative_velocity.length_squared();
+        if speed_squared == 0.0 {
+            return None;
+        }
+        let t = -self.to_player.dot(self.relative_velocity) / speed_squared;
+        (0.0..horizon)
+            .contains(&t)
+            .then(|| (t, self.to_player + self.relative_velocity * t))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Behavior {
+    // Face the player to shoot it.
+    Aggro,
+    // Get on a course that brings us near the player.
+    Intercept,
+    // Fly the burn our pursuit planner found, slingshots included.
+    Pursue,
+    // The player is getting away fast and we need to catch up.
+    MatchVelocities,
+    // Escape a collision course with a celestial body.
+    AvoidCrash,
+    // Dodge the player ramming us.
+    Evade,
+    // Circle the player while it sits on the ground.
+    Circle,
+    // Fly straight into the player.
+    Ram,
+    // Back off when the player gets too close.
+    KeepAway,
+}
+
+// How much ship types favour each behavior. Behaviors with no weight are never picked.
+#[derive(Clone, Copy)]
+pub struct AiProfile {
+    pub weights: &'static [(Behavior, f32)],
+    pub range: f32, // where we engage the player from
+}
+
+impl AiProfile {
+    pub const RAIDER: AiProfile = AiProfile {
+        weights: &[
+            (Behavior::Evade, 2.0),
+            (Behavior::Aggro, 1.0),
+            (Behavior::AvoidCrash, 0.85),
+            (Behavior::Circle, 0.7),
+            (Behavior::MatchVelocities, 0.6),
+            (Behavior::Intercept, 0.3),
+        ],
+        range: AGGRO_RANGE,
+    };
+
+    // Raiders that plan their pursuit through the bodies' gravity, like a good player would.
+    pub const VETERAN: AiProfile = AiProfile {
+        weights: &[
+            (Behavior::Evade, 2.0),
+            (Behavior::Aggro, 1.0),
+            (Behavior::AvoidCrash, 0.85),
+            (Behavior::Circle, 0.7),
+            (Behavior::MatchVelocities, 0.6),
+            (Behavior::Pursue, 0.5),
+            (Behavior::Intercept, 0.3),
+        ],
+        range: AGGRO_RANGE,
+    };
+
+    pub const KAMIKAZE: AiProfile = AiProfile {
+        weights: &[(Behavior::AvoidCrash, 0.85), (Behavior::Ram, 0.5)],
+        range: AGGRO_RANGE,
+    };
+
+    pub const SNIPER: AiProfile = AiProfile {
+        weights: &[
+            (Behavior::Evade, 2.0),
+            (Behavior::KeepAway, 1.2),
+            (Behavior::Aggro, 1.0),
+            (Behavior::AvoidCrash, 0.85),
+            (Behavior::MatchVelocities, 0.6),
+            (Behavior::Intercept, 0.3),
+        ],
+        range: 3500.0,
+    };
+
+    // Carriers stand back and let their drones do the fighting.
+    pub const CARRIER: AiProfile = AiProfile {
+        range: 4000.0,
+        ..AiProfile::SNIPER
+    };
+
+    // Bombers fly along with the player, laying mines rather than facing it.
+    pub const BOMBER: AiProfile = AiProfile {
+        weights: &[
+            (Behavior::Evade, 2.0),
+            (Behavior::AvoidCrash, 0.85),
+            (Behavior::Circle, 0.7),
+            (Behavior::MatchVelocities, 0.6),
+            (Behavior::Intercept, 0.3),
+        ],
+        range: AGGRO_RANGE,
+    };
+
+    pub fn uses(&self, behavior: Behavior) -> bool {
+        self.weights
+            .iter()
+            .any(|&(b, weight)| b == behavior && weight > 0.0)
+    }
+
+    // The behavior with the most utility in this situation.
+    pub fn choose(&self, current: Behavior, situation: &Situation) -> Behavior {
+        self.weights
+            .iter()
+            .map(|&(behavior, weight)| {
+                let score = behavior.score(situation, self);
+                let inertia = if behavior == current && score > 0.0 {
+                    INERTIA
+                } else {
+                    0.0
+                };
+                (behavior, weight * score + inertia)
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .map_or(current, |(behavior, _)| behavior)
+    }
+}
+
+// 0 below `from`, 1 above `to`, linear in between.
+fn ramp(x: f32, from: f32, to: f32) -> f32 {
+    ((x - from) / (to - from)).clamp(0.0, 1.0)
+}
+
+impl Behavior {
+    // Behaviors that have us go after the player, and can lure us into a crash.
+    pub fn chases_player(&self) -> bool {
+        matches!(self, Behavior::Intercept | Behavior::Pursue | Behavior::Ram)
+    }
+
+    // How relevant the behavior is, between 0 and 1.
+    pub fn score(&self, situation: &Situation, profile: &AiProfile) -> f32 {
+        let distance = situation.to_player.length();
+        match self {
+            Behavior::Aggro => (distance < profile.range) as u8 as f32,
+            Behavior::Intercept => 1.0,
+            Behavior::Pursue => situation.pursuit.is_some() as u8 as f32,
+            Behavior::MatchVelocities => ramp(
+                situation.relative_velocity.length(),
+                MATCH_DELTA_V_THRESHOLD,
+                MATCH_DELTA_V_THRESHOLD * 1.5,
+            ),
+            Behavior::AvoidCrash => situation
+                .time_to_impact
+                .is_some_and(|t| (0.0..AVOID_CRASH_TIME_HORIZON_S).contains(&t))
+                as u8 as f32,
+            Behavior::Evade => situation
+                .closest_approach(EVADE_TIME_HORIZON_S)
+                .filter(|(_, miss)| miss.length() < EVADE_MAX_GAP)
+                .map_or(0.0, |(t, _)| 1.0 - t / EVADE_TIME_HORIZON_S),
+            Behavior::Circle => situation.player_landed as u8 as f32,
+            Behavior::Ram => 1.0,
+            Behavior::KeepAway => {
+                let (back_off, flee) = KEEP_AWAY_FRACTIONS;
+                ramp(-distance, -profile.range * back_off, -profile.range * flee)
+            }
+        }
+    }
+
+    pub fn steer(&self, pilot: &mut Pilot, situation: &Situation, profile: &AiProfile) {
+        let Situation {
+            to_player,
+            relative_velocity,
+            ..
+        } = *situation;
+        match self {
+            Behavior::Aggro => {
+                pilot.face(situation.aim, MIN_ROTATION_THETA);
+                pilot.coast(0.2);
+            }
+            Behavior::Intercept => {
+                // Either aim towards towards player and accelerate to put on intercept course,
+                // Or turn around and brake in order to stop near the player.
+                let speed_dot = relative_velocity.length()
+                    * (-relative_velocity.normalize_or_zero()).dot(to_player.normalize_or_zero());
+                let should_brake = pilot.position_controller.should_brake(
+                    (to_player.length() - profile.range * 0.5).max(0.0),
+                    speed_dot,
+                );
+                if speed_dot > 0.25 && should_brake {
+                    // Our trajectory is aligned with the player's and we need to start reducing relative velocity.
+                    pilot.brake(relative_velocity, MIN_ROTATION_THETA * 2.0, 0.05);
+                } else {
+                    // Our trajectory is not aligned with the player and we need to adjust it.
+                    // We compute the direction we should face to match player trajectory.
+                    let wanted_dv = to_player.normalize_or_zero() * MATCH_DELTA_V_THRESHOLD;
+                    let drift = -relative_velocity - wanted_dv;
+                    if pilot.face(-drift, MIN_ROTATION_THETA) && drift.length() > MIN_DELTA_V {
+                        // We are aligned with desired trajectory and can start accelerating.
+                        pilot.burn(0.05);
+                    } else {
+                        // We are not aligned or already going fast enough towards desired trajectory.
+                        pilot.coast(0.05);
+                    }
+                }
+            }
+            Behavior::Pursue => match situation.pursuit {
+                Some(plan) if !plan.burnt(pilot.time.elapsed_seconds()) => {
+                    // The planner lights the burn up once we face its heading.
+                    let facing = pilot.face(plan.heading, MIN_ROTATION_THETA);
+                    match plan.burn_left(pilot.time.elapsed_seconds()) {
+                        Some(left) if facing => pilot.burn(left.min(0.05)),
+                        _ => pilot.coast(0.05),
+                    }
+                }
+                _ => {
+                    // Coasting along the planned course, ready to shoot.
+                    pilot.face(situation.aim, MIN_ROTATION_THETA);
+                    pilot.coast(0.2);
+                }
+            },
+            Behavior::MatchVelocities => {
+                let tts = pilot
+                    .position_controller
+                    .time_to_stop(relative_velocity.length());
+                pilot.brake(
+                    relative_velocity,
+                    PI / 8.0,
+                    (tts / 2.0 - 0.1).clamp(0.01, 0.25),
+                );
+            }
+            Behavior::AvoidCrash => {
+                // We aim for an escape trajectory facing away from the current gravity vector we are experiencing.
+                let escape = (-Vec2::Y.rotate(situation.gravity.normalize_or_zero())
+                    - situation.velocity.normalize_or_zero())
+                .normalize_or_zero();
+                pilot.evade(escape, PI / 4.0);
+            }
+            Behavior::Evade => {
+                // Sidestep away from where the player will pass by.
+                let dodge = situation
+                    .closest_approach(EVADE_TIME_HORIZON_S)
+                    .map_or(Vec2::ZERO, |(_, miss)| -miss.normalize_or_zero());
+                let dodge = if dodge == Vec2::ZERO {
+                    relative_velocity.perp().normalize_or_zero()
+                } else {
+                    dodge
+                };
+                pilot.evade(dodge, PI / 4.0);
+            }
+            Behavior::Circle => {
+                pilot.orbit(to_player, relative_velocity, CIRCLE_RADIUS, CIRCLE_SPEED);
+            }
+            Behavior::Ram => {
+                // Our velocity relative to the player should be straight at it.
+                let delta_v = to_player.normalize_or_zero() * RAM_SPEED + relative_velocity;
+                if pilot.face(delta_v, MIN_ROTATION_THETA) && delta_v.length() > MIN_DELTA_V {
+                    pilot.burn(0.05);
+                } else {
+                    pilot.coast(0.05);
+                }
+            }
+            Behavior::KeepAway => pilot.evade(-to_player, PI / 4.0),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{AiProfile, Behavior, Situation, MATCH_DELTA_V_THRESHOLD};
+    use crate::ai::pursuit::PursuitBurn;
+
+    fn cruising(to_player: Vec2, relative_velocity: Vec2) -> Situation {
+        Situation {
+            to_player,
+            relative_velocity,
+            aim: to_player,
+            velocity: Vec2::ZERO,
+            gravity: Vec2::ZERO,
+            time_to_impact: None,
+            player_landed: false,
+            pursuit: None,
+        }
+    }
+
+    #[test]
+    fn raiders_attack_close_and_chase_far() {
+        let raider = AiProfile::RAIDER;
+        let near = cruising(Vec2::new(500.0, 0.0), Vec2::ZERO);
+        assert_eq!(raider.choose(Behavior::Intercept, &near), Behavior::Aggro);
+        let far = cruising(Vec2::new(5000.0, 0.0), Vec2::ZERO);
+        assert_eq!(raider.choose(Behavior::Aggro, &far), Behavior::Intercept);
+        let crashing = Situation {
+            time_to_impact: Some(3.0),
+            ..far
+        };
+        assert_eq!(
+            raider.choose(Behavior::Intercept, &crashing),
+            Behavior::AvoidCrash
+        );
+    }
+
+    #[test]
+    fn raiders_dodge_rams() {
+        // Coming straight at us, half a second away.
+        let ram = cruising(Vec2::new(500.0, 10.0), Vec2::new(-1000.0, 0.0));
+        assert_eq!(
+            AiProfile::RAIDER.choose(Behavior::Aggro, &ram),
+            Behavior::Evade
+        );
+        // Passing by well wide of us.
+        let flyby = cruising(Vec2::new(1000.0, 500.0), Vec2::new(-1000.0, 0.0));
+        assert_eq!(
+            AiProfile::RAIDER.choose(Behavior::Aggro, &flyby),
+            Behavior::Aggro
+        );
+    }
+
+    #[test]
+    fn chasing_sticks_until_the_player_gets_well_away() {
+        let raider = AiProfile::RAIDER;
+        let fast = cruising(
+            Vec2::new(5000.0, 0.0),
+            Vec2::new(MATCH_DELTA_V_THRESHOLD * 1.2, 0.0),
+        );
+        assert_eq!(
+            raider.choose(Behavior::Intercept, &fast),
+            Behavior::Intercept
+        );
+        assert_eq!(
+            raider.choose(Behavior::MatchVelocities, &fast),
+            Behavior::MatchVelocities
+        );
+    }
+
+    #[test]
+    fn veterans_pursue_with_a_plan() {
+        let far = cruising(Vec2::new(5000.0, 0.0), Vec2::ZERO);
+        assert_eq!(
+            AiProfile::VETERAN.choose(Behavior::Intercept, &far),
+            Behavior::Intercept
+        );
+        let planned = Situation {
+            pursuit: Some(PursuitBurn {
+                heading: Vec2::X,
+                burn: 1.0,
+                ignition_at: 0.5,
+                started_at: None,
+                planned_at: 0.0,
+            }),
+            ..far
+        };
+        assert_eq!(
+            AiProfile::VETERAN.choose(Behavior::Intercept, &planned),
+            Behavior::Pursue
+        );
+        assert!(!AiProfile::RAIDER.uses(Behavior::Pursue));
+    }
+
+    #[test]
+    fn snipers_keep_their_distance() {
+        let sniper = AiProfile::SNIPER;
+        let close = cruising(Vec2::new(500.0, 0.0), Vec2::ZERO);
+        assert_eq!(sniper.choose(Behavior::Aggro, &close), Behavior::KeepAway);
+        let in_range = cruising(Vec2::new(sniper.range * 0.8, 0.0), Vec2::ZERO);
+        assert_eq!(
+            sniper.choose(Behavior::KeepAway, &in_range),
+            Behavior::Aggro
+        );
+        // Where raiders would still be closing in.
+        assert_eq!(
+            AiProfile::RAIDER.choose(Behavior::Intercept, &in_range),
+            Behavior::Intercept
+        );
+    }
+}
@@ -0,0 +1,71 @@
+use bevy::prelude::*;
+
+use super::{
+    orientation_controller::{OrientationController, MIN_ROTATION_THETA},
+    position_controller::PositionController,
+};
+
+// Below this, a velocity change isn't worth a burn.
+pub const MIN_DELTA_V: f32 = 25.0;
+
+// The building blocks behaviors are made of.
+// Each maneuver issues commands to the ship's controllers, that run until the next update.
+pub struct Pilot<'a> {
+    pub time: &'a Time,
+    pub orientation: f32,
+    pub angular_velocity: f32,
+    pub orientation_controller: &'a mut OrientationController,
+    pub position_controller: &'a mut PositionController,
+}
+
+impl Pilot<'_> {
+    // Turns the nose towards `direction`, and tells whether it's within `epsilon` of it.
+    pub fn face(&mut self, direction: Vec2, epsilon: f32) -> bool {
+        self.orientation_controller
+            .target(direction.y.atan2(direction.x));
+        self.orientation_controller.update_command(
+            self.time,
+            self.orientation,
+            self.angular_velocity,
+        );
+        self.orientation_controller
+            .at_target(self.orientation, epsilon)
+    }
+
+    pub fn burn(&mut self, duration: f32) {
+        self.position_controller.accelerate(self.time, duration);
+    }
+
+    pub fn coast(&mut self, duration: f32) {
+        self.position_controller.sleep(self.time, duration);
+    }
+
+    // Cancels the velocity relative to a target: thrusts along `relative_velocity` once facing it.
+    pub fn brake(&mut self, relative_velocity: Vec2, epsilon: f32, duration: f32) {
+        if self.face(relative_velocity, epsilon) {
+            self.burn(duration);
+        }
+    }
+
+    // Gets away in `direction` at full thrust, as soon as we roughly face it.
+    pub fn evade(&mut self, direction: Vec2, epsilon: f32) {
+        if self.face(direction, epsilon) {
+            self.burn(1.0);
+        }
+    }
+
+    // Circles a moving point at `radius` and `speed`.
+    // `offset` goes from us to the point, `relative_velocity` is its velocity relative to ours.
+    pub fn orbit(&mut self, offset: Vec2, relative_velocity: Vec2, radius: f32, speed: f32) {
+        let inwards = offset.normalize_or_zero();
+        let radial_error = (offset.length() - radius).clamp(-speed, speed);
+        // Counter-clockwise around the point, correcting our distance to it.
+        let wanted = -inwards.perp() * speed + inwards * radial_error;
+        let delta_v = wanted + relative_velocity;
+        if self.face(delta_v, MIN_ROTATION_THETA) && delta_v.length() > MIN_DELTA_V {
+            self.burn(0.05);
+        } else {
+            self.coast(0.05);
+        }
+    }
+}
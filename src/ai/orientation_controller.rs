@@ -4,7 +4,7 @@ use bevy::prelude::*;
 
 use crate::{alien_ship::ALIEN_SHIP_MASS, GLOBAL_IMPULSE_DURATION_MULT};
 
-const ANGULAR_INERTIA: f32 = 0.5 * ALIEN_SHIP_MASS * 32.0 * 32.0;
+pub const ALIEN_SHIP_ANGULAR_INERTIA: f32 = 0.5 * ALIEN_SHIP_MASS * 32.0 * 32.0;
 const STABILIZE_ANGULAR_VELOCITY_THRESHOLD: f32 = 4.0 * 2.0 * PI;
 pub const MIN_ROTATION_THETA: f32 = PI / 16.0; // We're close enough.
 
@@ -12,6 +12,7 @@ pub const MIN_ROTATION_THETA: f32 = PI / 16.0; // We're close enough.
 pub struct OrientationController {
     pub rotation_target: Option<f32>,
     pub torque_available: f32,
+    pub angular_inertia: f32,
     pub current_command: (f32, f32), // torque, time of stop
 }
 
@@ -21,9 +22,15 @@ impl OrientationController {
             rotation_target: None,
             current_command: (0.0, 0.0),
             torque_available,
+            angular_inertia: ALIEN_SHIP_ANGULAR_INERTIA,
         }
     }
 
+    pub fn with_angular_inertia(mut self, angular_inertia: f32) -> Self {
+        self.angular_inertia = angular_inertia;
+        self
+    }
+
     pub fn update_command(&mut self, time: &Time, p0: f32, v0: f32) {
         if self.rotation_target.is_some() {
             let (torque, delta_time) = self.torque_needed(p0, v0);
@@ -62,9 +69,16 @@ impl OrientationController {
         self.rotation_target = Some(OrientationController::to_bounded_positive_angle(target));
     }
 
+    // Turning `arc` from rest, flipping the torque halfway.
+    pub fn time_to_turn(&self, arc: f32) -> f32 {
+        2.0 * (arc.abs() * self.angular_inertia
+            / (self.torque_available * GLOBAL_IMPULSE_DURATION_MULT))
+            .sqrt()
+    }
+
     #[inline]
     pub fn time_to_stop(&self, v0: f32) -> f32 {
-        v0.abs() * ANGULAR_INERTIA / (self.torque_available * GLOBAL_IMPULSE_DURATION_MULT)
+        v0.abs() * self.angular_inertia / (self.torque_available * GLOBAL_IMPULSE_DURATION_MULT)
     }
 
     pub fn should_brake(&self, current_orientation: f32, angular_velocity: f32) -> Option<bool> {
@@ -0,0 +1,304 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_rapier2d::{dynamics::Velocity, geometry::Collider};
+
+use crate::{
+    ai::orientation_controller::{OrientationController, MIN_ROTATION_THETA},
+    alien_ship::{AlienShipMarker, ALIEN_SHIP_MASS},
+    course_planner::{
+        collect_celestial_bodies, scaled_radius, ComputedTrajectory, PlannedBodies,
+        ENEMY_PLAN_DURATION, ENEMY_PLAN_STEP_DT, ENEMY_PLAN_TOLERANCE,
+    },
+    gravity::{plan_course, CoursePlanning, PlannedShip},
+    player::PlayerMarker,
+    simulation::SimulationMode,
+    thruster::Thruster,
+};
+
+use super::AGGRO_RANGE;
+
+// Candidate burns are headings around the direction to the player, for each of these durations, plus coasting.
+const PURSUIT_HEADINGS: u32 = 8;
+const PURSUIT_BURNS_S: [f32; 2] = [0.5, 1.5];
+const PURSUIT_CANDIDATES: usize = PURSUIT_HEADINGS as usize * PURSUIT_BURNS_S.len() + 1;
+// How many candidate courses may be dispatched each frame, across all ships.
+const MAX_PURSUIT_CANDIDATES_PER_FRAME: usize = 3 * PURSUIT_CANDIDATES;
+const PURSUIT_REPLAN_S: f32 = 2.0;
+const PURSUIT_PLAN_TTL_S: f32 = 4.0;
+// A burn that couldn't start this soon after its planned ignition misses the course it was scored on.
+const PURSUIT_MAX_IGNITION_LAG_S: f32 = 0.25;
+// Costs, in world units of miss distance.
+const PURSUIT_COST_PER_S: f32 = 100.0; // to get there
+const PURSUIT_COST_PER_BURN_S: f32 = 200.0;
+const PURSUIT_SAFE_ALTITUDE: f32 = 200.0;
+const PURSUIT_COST_PER_RISK: f32 = 10.0; // per unit of altitude below the safe one
+
+// A burn that puts us on a course to meet the player, possibly slingshotting around a body.
+// It lights up once we face `heading`, no sooner than planned, and lasts `burn` seconds.
+#[derive(Clone, Copy)]
+pub struct PursuitBurn {
+    pub heading: Vec2,
+    pub burn: f32, // zero to coast along
+    pub ignition_at: f32,
+    pub started_at: Option<f32>,
+    pub planned_at: f32,
+}
+
+impl PursuitBurn {
+    // What's left of the burn, once it started.
+    pub fn burn_left(&self, now: f32) -> Option<f32> {
+        self.started_at.map(|at| (at + self.burn - now).max(0.0))
+    }
+
+    pub fn burnt(&self, now: f32) -> bool {
+        self.burn <= 0.0 || self.burn_left(now) == Some(0.0)
+    }
+}
+
+// Ships that plan their pursuit of the player through the bodies' gravity.
+#[derive(Component, Default)]
+pub struct Pursuit {
+    plan: Option<PursuitBurn>,
+    requested_at: Option<f32>,
+    task: Option<Task<Option<PursuitBurn>>>,
+}
+
+impl Pursuit {
+    pub fn current(&self, now: f32) -> Option<PursuitBurn> {
+        self.plan
+            .filter(|plan| now - plan.planned_at < PURSUIT_PLAN_TTL_S)
+    }
+}
+
+type PursuingShip<'a> = (
+    &'a Transform,
+    &'a Velocity,
+    &'a Collider,
+    &'a Thruster,
+    &'a OrientationController,
+    &'a mut Pursuit,
+);
+
+// Where the candidate course leaves us relative to the player. Lower is better.
+// `player_path` holds the player's predicted positions, on the same steps as the course.
+pub fn pursuit_cost(course: &CoursePlanning, player_path: &[Vec2], burn: f32) -> f32 {
+    if course.impact.is_some() {
+        return f32::INFINITY;
+    }
+    let Some((step, miss)) = course
+        .path
+        .iter()
+        .zip(player_path)
+        .map(|(&(pos, _), &player)| pos.distance(player))
+        .enumerate()
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+    else {
+        return f32::INFINITY;
+    };
+    let time = (step + 1) as f32 * ENEMY_PLAN_STEP_DT;
+    let risk = (PURSUIT_SAFE_ALTITUDE - course.closest_flyby).max(0.0);
+    (miss - AGGRO_RANGE * 0.5).max(0.0)
+        + time * PURSUIT_COST_PER_S
+        + burn * PURSUIT_COST_PER_BURN_S
+        + risk * PURSUIT_COST_PER_RISK
+}
+
+fn candidates(towards: Vec2) -> impl Iterator<Item = (Vec2, f32)> {
+    let towards = towards.normalize_or_zero();
+    std::iter::once((towards, 0.0)).chain((0..PURSUIT_HEADINGS).flat_map(move |i| {
+        let heading =
+            Vec2::from_angle(i as f32 * 2.0 * PI / PURSUIT_HEADINGS as f32).rotate(towards);
+        PURSUIT_BURNS_S.map(|burn| (heading, burn))
+    }))
+}
+
+pub fn plan_pursuits(
+    time: Res<Time>,
+    player: Query<(&Transform, &Velocity, &ComputedTrajectory), With<PlayerMarker>>,
+    mut ships: Query<PursuingShip, With<AlienShipMarker>>,
+    bodies: PlannedBodies,
+) {
+    let Ok((pt, pv, player_trajectory)) = player.get_single() else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    let steps = (ENEMY_PLAN_DURATION / ENEMY_PLAN_STEP_DT).ceil() as usize;
+    let mut snapshot = None;
+    let mut budget = MAX_PURSUIT_CANDIDATES_PER_FRAME;
+    for (t, v, collider, thruster, orientation_controller, mut pursuit) in ships.iter_mut() {
+        if budget < PURSUIT_CANDIDATES {
+            break;
+        }
+        // Don't cut a burn short with a plan that assumed we'd start from rest.
+        let burning = pursuit
+            .plan
+            .is_some_and(|plan| plan.started_at.is_some() && !plan.burnt(now));
+        if burning
+            || pursuit.task.is_some()
+            || pursuit
+                .requested_at
+                .is_some_and(|at| now - at < PURSUIT_REPLAN_S)
+        {
+            continue;
+        }
+        budget -= PURSUIT_CANDIDATES;
+        // Where the player will be, on the steps of our plans.
+        let player_path: Vec<Vec2> = (1..=steps)
+            .map(|i| {
+                let dt = i as f32 * ENEMY_PLAN_STEP_DT;
+                player_trajectory
+                    .position_at(now + dt)
+                    .unwrap_or(pt.translation.xy() + pv.linvel * dt)
+            })
+            .collect();
+        let bodies = snapshot
+            .get_or_insert_with(|| collect_celestial_bodies(&bodies))
+            .bodies
+            .clone();
+        let ship = PlannedShip {
+            start_time: 0.0,
+            pos: t.translation.xy(),
+            velocity: v.linvel,
+            radius: scaled_radius(collider, t),
+            thrust: None,
+        };
+        let forward = t.up().xy();
+        let candidates: Vec<PlannedShip> = candidates(player_path[0] - ship.pos)
+            .map(|(heading, burn)| PlannedShip {
+                // The engine stays off while we turn towards the heading.
+                thrust: (burn > 0.0).then(|| {
+                    thruster
+                        .profile(heading, ALIEN_SHIP_MASS, burn)
+                        .with_ignition_delay(
+                            orientation_controller.time_to_turn(forward.angle_between(heading)),
+                        )
+                }),
+                ..ship.clone()
+            })
+            .collect();
+        pursuit.requested_at = Some(now);
+        pursuit.task = Some(AsyncComputeTaskPool::get().spawn(async move {
+            candidates
+                .iter()
+                .map(|ship| {
+                    let course = plan_course(
+                        ENEMY_PLAN_DURATION,
+                        ENEMY_PLAN_STEP_DT,
+                        ENEMY_PLAN_TOLERANCE,
+                        ship,
+                        &bodies,
+                    );
+                    let burn = ship.thrust.map_or(0.0, |thrust| thrust.burn_duration);
+                    (ship, burn, pursuit_cost(&course, &player_path, burn))
+                })
+                .filter(|(_, _, cost)| cost.is_finite())
+                .min_by(|a, b| a.2.total_cmp(&b.2))
+                .map(|(ship, burn, _)| PursuitBurn {
+                    heading: ship
+                        .thrust
+                        .map_or(player_path[0] - ship.pos, |thrust| thrust.direction),
+                    burn,
+                    ignition_at: now + ship.thrust.map_or(0.0, |thrust| thrust.ignition_delay),
+                    started_at: None,
+                    planned_at: now,
+                })
+        }));
+    }
+}
+
+pub fn apply_pursuits(
+    time: Res<Time>,
+    mode: Res<SimulationMode>,
+    mut ships: Query<(&Transform, &mut Pursuit)>,
+) {
+    // In deterministic mode, plans must land on the tick they were requested on.
+    let wait = matches!(*mode, SimulationMode::Deterministic { .. });
+    let now = time.elapsed_seconds();
+    for (t, mut pursuit) in ships.iter_mut() {
+        if let Some(task) = pursuit.task.as_mut() {
+            if wait || task.is_finished() {
+                let plan = block_on(task);
+                pursuit.plan = plan;
+                pursuit.task = None;
+            }
+        }
+        let Some(plan) = pursuit.plan.as_mut() else {
+            continue;
+        };
+        if plan.burn <= 0.0 || plan.started_at.is_some() || now < plan.ignition_at {
+            continue;
+        }
+        if t.up().xy().angle_between(plan.heading).abs() < MIN_ROTATION_THETA {
+            plan.started_at = Some(now);
+        } else if now > plan.ignition_at + PURSUIT_MAX_IGNITION_LAG_S {
+            // We turned too slowly, or the plan came in too late: plan again from here.
+            pursuit.plan = None;
+            pursuit.requested_at = None;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{pursuit_cost, PursuitBurn};
+    use crate::gravity::{CoursePlanning, PlannedImpact};
+
+    fn course(path: &[Vec2], closest_flyby: f32) -> CoursePlanning {
+        CoursePlanning {
+            path: path.iter().map(|&pos| (pos, closest_flyby)).collect(),
+            closest_flyby,
+            impact: None,
+            velocity: Vec2::ZERO,
+        }
+    }
+
+    #[test]
+    fn pursuit_prefers_safe_courses_that_meet_the_player_early() {
+        let player = [Vec2::new(5000.0, 0.0); 4];
+        let meets_late = course(&[Vec2::ZERO, Vec2::ZERO, Vec2::ZERO, player[0]], 1000.0);
+        let meets_early = course(&[Vec2::ZERO, player[0], player[0], player[0]], 1000.0);
+        assert!(pursuit_cost(&meets_early, &player, 1.0) < pursuit_cost(&meets_late, &player, 1.0));
+        // The same, grazing a body.
+        let risky = course(&[Vec2::ZERO, player[0], player[0], player[0]], 10.0);
+        assert!(pursuit_cost(&risky, &player, 1.0) > pursuit_cost(&meets_early, &player, 1.0));
+        // Coasting is cheaper than burning to the same result.
+        assert!(
+            pursuit_cost(&meets_early, &player, 0.0) < pursuit_cost(&meets_early, &player, 1.0)
+        );
+        let crashing = CoursePlanning {
+            impact: Some(PlannedImpact { time: 1.0, body: 0 }),
+            ..course(&[Vec2::ZERO], 0.0)
+        };
+        assert_eq!(pursuit_cost(&crashing, &player, 0.0), f32::INFINITY);
+    }
+
+    #[test]
+    fn burns_last_as_long_as_planned_once_lit() {
+        let plan = PursuitBurn {
+            heading: Vec2::X,
+            burn: 1.5,
+            ignition_at: 10.0,
+            started_at: None,
+            planned_at: 9.0,
+        };
+        // Still turning.
+        assert_eq!(plan.burn_left(11.0), None);
+        assert!(!plan.burnt(11.0));
+        // Lit up late, it still burns for the whole duration.
+        let lit = PursuitBurn {
+            started_at: Some(10.5),
+            ..plan
+        };
+        assert_eq!(lit.burn_left(11.0), Some(1.0));
+        assert!(lit.burnt(12.0));
+        // Coasting plans never burn.
+        let coasting = PursuitBurn { burn: 0.0, ..plan };
+        assert!(coasting.burnt(9.0));
+    }
+}
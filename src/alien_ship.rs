@@ -1,18 +1,20 @@
 use std::f32::consts::PI;
 
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_rapier2d::dynamics::Velocity;
 
 use crate::{
     ai::{
-        orientation_controller::OrientationController, position_controller::PositionController,
-        AIControllerQueues, AGGRO_RANGE,
+        behaviors::AiProfile, orientation_controller::OrientationController,
+        position_controller::PositionController, AIControllerQueues, ShipAi, AGGRO_RANGE,
     },
     camera::GameCameraMarker,
+    gravity::AffectedByGravity,
     impulses_aggregator::AddExternalImpulse,
-    lasers::{self, Laser, LaserAbility, LaserOrigin},
+    lasers::{self, Laser, LaserAbility, LaserOrigin, LASER_LIFETIME_S},
     particles::thrusters::spawn_rotation_thruster_cone,
-    player::PlayerMarker,
+    player::{PlayerMarker, PLAYER_LASER_DAMAGE},
+    simulation::SimulationRng,
     thruster::Thruster,
     ui::GameSettings,
     GLOBAL_IMPULSE_DURATION_MULT,
@@ -22,7 +24,11 @@ pub const ALIEN_SHIP_DRIVE_ENGINE_IMPULSE: f32 = 8.0;
 pub const ALIEN_SHIP_ROTATION_IMPULSE: f32 = 6.0 * ALIEN_SHIP_DRIVE_ENGINE_IMPULSE;
 pub const ALIEN_SHIP_MASS: f32 = 1.0;
 
-pub const ALIEN_SHIP_LASER_COOLDOWN_S: f32 = 0.33;
+const ALIEN_SHIP_LASER_COOLDOWN_S: f32 = 0.33;
+const ALIEN_SHIP_LASER_SPEED: f32 = 1500.0;
+// Bombers only lay mines this close to the player, for them to drift across its path.
+const MINE_LAYING_RANGE: f32 = 3000.0;
+const MINE_LIFETIME_S: f32 = 30.0;
 
 const MAX_SHOOT_THETA: f32 = PI / 16.0;
 
@@ -45,25 +51,184 @@ This allows the player to trick the enemy into crashing or being slingshot while
 #[derive(Component)]
 pub struct AlienShipMarker;
 
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Archetype {
+    Raider,
+    Veteran,
+    Kamikaze,
+    Sniper,
+    Carrier,
+    Drone, // launched by carriers
+    Bomber,
+}
+
+#[derive(Clone, Copy)]
+pub enum Weapon {
+    Laser {
+        damage: f32,
+        speed: f32,
+        lifetime: f32,
+        range: f32,
+    },
+    Mines {
+        damage: f32,
+    },
+    Hangar {
+        max_drones: usize,
+    },
+    Hull, // kamikazes are their own weapon
+}
+
+pub struct ArchetypeStats {
+    pub hp: f32,
+    pub engine: f32,   // times the base engine impulse
+    pub rotation: f32, // times the base rotation impulse
+    pub scale: f32,
+    pub tint: Color, // on the shared enemy sprite, until each archetype gets its own
+    pub weapon: Weapon,
+    pub cooldown: f32,
+    pub profile: AiProfile,
+}
+
+impl Archetype {
+    pub fn stats(&self) -> ArchetypeStats {
+        let laser = Weapon::Laser {
+            damage: 10.0,
+            speed: ALIEN_SHIP_LASER_SPEED,
+            lifetime: LASER_LIFETIME_S,
+            range: AGGRO_RANGE,
+        };
+        // A raider goes down to a single shot from the player.
+        let raider = ArchetypeStats {
+            hp: PLAYER_LASER_DAMAGE,
+            engine: 1.0,
+            rotation: 1.0,
+            scale: 1.0,
+            tint: Color::WHITE,
+            weapon: laser,
+            cooldown: ALIEN_SHIP_LASER_COOLDOWN_S,
+            profile: AiProfile::RAIDER,
+        };
+        match self {
+            Archetype::Raider => raider,
+            Archetype::Veteran => ArchetypeStats {
+                tint: Color::rgb(1.0, 0.85, 0.4),
+                profile: AiProfile::VETERAN,
+                ..raider
+            },
+            Archetype::Kamikaze => ArchetypeStats {
+                hp: 25.0,
+                engine: 1.5,
+                rotation: 1.2,
+                scale: 0.8,
+                tint: Color::rgb(1.0, 0.35, 0.3),
+                weapon: Weapon::Hull,
+                profile: AiProfile::KAMIKAZE,
+                ..raider
+            },
+            Archetype::Sniper => ArchetypeStats {
+                hp: 40.0,
+                engine: 0.8,
+                tint: Color::rgb(0.5, 0.8, 1.0),
+                weapon: Weapon::Laser {
+                    damage: 35.0,
+                    speed: 1000.0,
+                    lifetime: 4.0,
+                    range: AiProfile::SNIPER.range,
+                },
+                cooldown: 2.5,
+                profile: AiProfile::SNIPER,
+                ..raider
+            },
+            Archetype::Carrier => ArchetypeStats {
+                hp: 250.0,
+                engine: 0.4,
+                rotation: 0.5,
+                scale: 2.0,
+                tint: Color::rgb(0.6, 1.0, 0.6),
+                weapon: Weapon::Hangar { max_drones: 4 },
+                cooldown: 4.0,
+                profile: AiProfile::CARRIER,
+            },
+            Archetype::Drone => ArchetypeStats {
+                hp: 15.0,
+                engine: 1.3,
+                rotation: 1.5,
+                scale: 0.5,
+                tint: Color::rgb(0.6, 1.0, 0.6),
+                weapon: Weapon::Laser {
+                    damage: 5.0,
+                    speed: ALIEN_SHIP_LASER_SPEED,
+                    lifetime: LASER_LIFETIME_S,
+                    range: AGGRO_RANGE,
+                },
+                cooldown: 0.5,
+                ..raider
+            },
+            Archetype::Bomber => ArchetypeStats {
+                hp: 80.0,
+                engine: 0.8,
+                scale: 1.3,
+                tint: Color::rgb(0.9, 0.5, 1.0),
+                weapon: Weapon::Mines { damage: 30.0 },
+                cooldown: 1.5,
+                profile: AiProfile::BOMBER,
+                ..raider
+            },
+        }
+    }
+}
+
+impl Weapon {
+    // Where to point the weapon at the player. `accuracy` blends between where the player is and where our shots would meet it.
+    pub fn aim(
+        &self,
+        to_player: Vec2,
+        relative_velocity: Vec2,
+        relative_acceleration: Vec2,
+        accuracy: f32,
+    ) -> Vec2 {
+        let Weapon::Laser {
+            speed, lifetime, ..
+        } = *self
+        else {
+            return to_player;
+        };
+        match lasers::firing_solution(to_player, relative_velocity, relative_acceleration, speed) {
+            Some((lead, t)) if t < lifetime => to_player.lerp(lead, accuracy),
+            _ => to_player,
+        }
+    }
+}
+
+type AlienShip<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    &'a AffectedByGravity,
+    Option<&'a Archetype>,
+    &'a OrientationController,
+    &'a PositionController,
+    &'a mut LaserAbility,
+    &'a mut Thruster,
+    Has<ShipAi>,
+);
+
+// What alien ships leave behind them: lasers, mines and thruster particles.
+#[derive(SystemParam)]
+pub struct ShipSpawns<'w, 's> {
+    commands: Commands<'w, 's>,
+    rng: ResMut<'w, SimulationRng>,
+    settings: Res<'w, GameSettings>,
+}
+
 pub fn update(
-    mut commands: Commands,
-    settings: Res<GameSettings>,
+    mut spawns: ShipSpawns,
     mut orientation_controller_queue: ResMut<AIControllerQueues>,
     time: Res<Time>,
     mut impulses: EventWriter<AddExternalImpulse>,
-    player: Query<&Transform, With<PlayerMarker>>,
-    mut query: Query<
-        (
-            Entity,
-            &Transform,
-            &Velocity,
-            &OrientationController,
-            &PositionController,
-            &mut LaserAbility,
-            &mut Thruster,
-        ),
-        With<AlienShipMarker>,
-    >,
+    player: Query<(&Transform, &Velocity, &AffectedByGravity), With<PlayerMarker>>,
+    mut query: Query<AlienShip, With<AlienShipMarker>>,
     camera: Query<(&Transform, &OrthographicProjection), With<GameCameraMarker>>,
 ) {
     // The actual AI is going to be a bit tricky.
@@ -77,40 +242,81 @@ pub fn update(
             min: cam_area.min + cam_pos,
             max: cam_area.max + cam_pos,
         };
-        if let Ok(player_t) = player.get_single() {
+        if let Ok((player_t, player_v, player_gravity)) = player.get_single() {
             for (
                 entity,
                 t,
                 v,
+                gravity,
+                archetype,
                 orientation_controller,
                 position_controller,
                 mut laser_ability,
                 mut thruster,
+                has_ai,
             ) in query.iter_mut()
             {
                 let mut angular_impulse = 0.0;
 
                 let local_forward = t.up().xy();
                 let d = (player_t.translation - t.translation).xy();
-                let orientation_to_player = local_forward.angle_between(d);
-                if ENABLE_SHOOTING
-                    && d.length() < AGGRO_RANGE
-                    && orientation_to_player.abs() < MAX_SHOOT_THETA
-                    && laser_ability.ready(&time)
-                {
-                    lasers::spawn(
-                        &mut commands,
-                        t.translation.xy()
-                            + v.linvel * time.delta_seconds()
-                            + t.up().xy().normalize() * 60.0,
-                        local_forward.rotate(Vec2 { x: 1500.0, y: 0.0 }) + v.linvel,
-                        Laser {
-                            origin: LaserOrigin::Enemy,
-                            damage: 10.0,
-                            shot_at: time.elapsed_seconds(),
-                        },
-                    );
-                    laser_ability.last_shot = Some(time.elapsed_seconds());
+                // Motherships have no archetype: their hardpoints fight for them.
+                let weapon = archetype.map_or(Weapon::Hull, |archetype| archetype.stats().weapon);
+                let ready = ENABLE_SHOOTING && laser_ability.ready(&time);
+                match weapon {
+                    Weapon::Laser {
+                        damage,
+                        speed,
+                        lifetime,
+                        range,
+                    } => {
+                        let aim = weapon.aim(
+                            d,
+                            player_v.linvel - v.linvel,
+                            player_gravity.last_acceleration - gravity.last_acceleration,
+                            spawns.settings.difficulty.aim_accuracy(),
+                        );
+                        if ready
+                            && d.length() < range
+                            && local_forward.angle_between(aim).abs() < MAX_SHOOT_THETA
+                        {
+                            lasers::spawn(
+                                &mut spawns.commands,
+                                t.translation.xy()
+                                    + v.linvel * time.delta_seconds()
+                                    + local_forward.normalize() * 60.0 * t.scale.x,
+                                local_forward.rotate(Vec2 { x: speed, y: 0.0 }) + v.linvel,
+                                Laser {
+                                    origin: LaserOrigin::Enemy,
+                                    shooter: entity,
+                                    damage,
+                                    shot_at: time.elapsed_seconds(),
+                                    lifetime,
+                                },
+                            );
+                            laser_ability.last_shot = Some(time.elapsed_seconds());
+                        }
+                    }
+                    Weapon::Mines { damage } => {
+                        if ready && d.length() < MINE_LAYING_RANGE {
+                            // Dropped behind us, to coast along the orbit we're on.
+                            lasers::spawn_mine(
+                                &mut spawns.commands,
+                                t.translation.xy() - local_forward.normalize() * 80.0 * t.scale.x,
+                                v.linvel,
+                                Laser {
+                                    origin: LaserOrigin::Enemy,
+                                    shooter: entity,
+                                    damage,
+                                    shot_at: time.elapsed_seconds(),
+                                    lifetime: MINE_LIFETIME_S,
+                                },
+                            );
+                            laser_ability.last_shot = Some(time.elapsed_seconds());
+                        }
+                    }
+                    // Drones are launched by `alien_waves::launch_drones`.
+                    Weapon::Hangar { .. } | Weapon::Hull => {}
                 }
 
                 let mut request_dynamics_controllers_update = false;
@@ -121,8 +327,9 @@ pub fn update(
                     let xy = t.translation.xy();
                     if abs_cam_area.contains(xy) {
                         spawn_rotation_thruster_cone(
-                            &mut commands,
-                            settings.entities_quantity,
+                            &mut spawns.commands,
+                            &mut spawns.rng.particles,
+                            spawns.settings.entities_quantity,
                             &time,
                             xy + t.right().xy().normalize()
                                 * particle_distance
@@ -145,7 +352,8 @@ pub fn update(
                     thruster.release(time.delta_seconds());
                 }
 
-                if request_dynamics_controllers_update {
+                // Motherships steer themselves, in `mothership::fly_motherships`.
+                if request_dynamics_controllers_update && has_ai {
                     orientation_controller_queue.controllers.push_back(entity);
                 }
                 impulses.send(AddExternalImpulse {
@@ -1,26 +1,34 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_rapier2d::{
     dynamics::{Ccd, Damping, RigidBody, Velocity},
-    geometry::{ActiveEvents, Collider, ColliderMassProperties},
+    geometry::{Collider, ColliderMassProperties},
 };
 use rand::prelude::*;
 use std::f32::consts::PI;
 
 use crate::{
     ai::{
-        orientation_controller::OrientationController, position_controller::PositionController,
+        behaviors::Behavior,
+        orientation_controller::{OrientationController, ALIEN_SHIP_ANGULAR_INERTIA},
+        position_controller::PositionController,
+        pursuit::Pursuit,
         AIControllerQueues, ShipAi,
     },
     alien_ship::{
-        AlienShipMarker, ALIEN_SHIP_DRIVE_ENGINE_IMPULSE, ALIEN_SHIP_LASER_COOLDOWN_S,
-        ALIEN_SHIP_MASS, ALIEN_SHIP_ROTATION_IMPULSE,
+        AlienShipMarker, Archetype, Weapon, ALIEN_SHIP_DRIVE_ENGINE_IMPULSE, ALIEN_SHIP_MASS,
+        ALIEN_SHIP_ROTATION_IMPULSE,
     },
     camera::game_layer,
+    collisions_handler,
     course_planner::ComputedTrajectory,
+    death::KillCredit,
+    gameplay_events::WaveStarted,
     gravity::AffectedByGravity,
     healthpoints::HealthPoints,
     lasers::LaserAbility,
+    mothership::{self, BOSS_WAVE_EVERY},
     player::PlayerMarker,
+    simulation::SimulationRng,
     thruster::Thruster,
     ui::{Difficulty, EntitiesQuantity, GameSettings},
 };
@@ -28,6 +36,45 @@ use rand::distributions::Uniform;
 
 const ENABLE_ENEMIES: bool = true;
 const WAVE_DURATION_S: f32 = 30.0;
+// From which wave on each archetype shows up, and one ship in how many it replaces a raider.
+// Earlier entries win when several would replace the same ship.
+const WAVE_MIX: [(Archetype, u32, u32); 5] = [
+    (Archetype::Carrier, 5, 20),
+    (Archetype::Bomber, 4, 8),
+    (Archetype::Sniper, 3, 6),
+    (Archetype::Kamikaze, 2, 4),
+    (Archetype::Veteran, 2, 5),
+];
+const DRONE_LAUNCH_SPEED: f32 = 200.0;
+
+// Launched by, and counted against, a carrier.
+#[derive(Component)]
+pub struct Drone {
+    pub carrier: Entity,
+}
+
+// What it takes to bring alien ships into the world.
+#[derive(SystemParam)]
+pub struct AlienSpawner<'w, 's> {
+    commands: Commands<'w, 's>,
+    asset_server: Res<'w, AssetServer>,
+    controller_queue: ResMut<'w, AIControllerQueues>,
+    settings: Res<'w, GameSettings>,
+}
+
+impl AlienSpawner<'_, '_> {
+    fn spawn(&mut self, archetype: Archetype, pos: Vec2, velocity: Vec2) -> Entity {
+        spawn_alien(
+            &mut self.commands,
+            &self.asset_server,
+            &mut self.controller_queue,
+            self.settings.difficulty,
+            archetype,
+            pos,
+            velocity,
+        )
+    }
+}
 
 #[derive(Resource)]
 pub struct AlienWave {
@@ -42,6 +89,13 @@ pub fn setup(mut commands: Commands) {
     });
 }
 
+pub fn wave_archetype(wave: u32, i: u32) -> Archetype {
+    WAVE_MIX
+        .iter()
+        .find(|&&(_, from_wave, every)| wave >= from_wave && i % every == every - 1)
+        .map_or(Archetype::Raider, |&(archetype, _, _)| archetype)
+}
+
 fn enemies_per_wave_count(wave: &ResMut<AlienWave>, settings: &Res<GameSettings>) -> u32 {
     wave.current_wave
         * match settings.entities_quantity {
@@ -65,22 +119,21 @@ fn should_start_next_wave(
 }
 
 pub fn update(
-    mut commands: Commands,
-    mut controller_queue: ResMut<AIControllerQueues>,
+    mut spawner: AlienSpawner,
+    mut rng: ResMut<SimulationRng>,
     time: Res<Time>,
-    asset_server: Res<AssetServer>,
     player: Query<(&Transform, &Velocity), With<PlayerMarker>>,
     mut wave: ResMut<AlienWave>,
-    settings: Res<GameSettings>,
     enemies_query: Query<Entity, With<AlienShipMarker>>,
+    mut wave_started: EventWriter<WaveStarted>,
 ) {
     if let Ok((player_transform, player_velocity)) = player.get_single() {
-        let mut rng = rand::thread_rng();
+        let rng = &mut rng.gameplay;
 
-        if should_start_next_wave(&wave, &time, &settings, enemies_query) {
+        if should_start_next_wave(&wave, &time, &spawner.settings, enemies_query) {
             let angle_side = Uniform::new(0.0, PI * 2.0);
             let radius_side = Uniform::new(50.0, 2000.0);
-            let n_to_spawn = enemies_per_wave_count(&wave, &settings);
+            let n_to_spawn = enemies_per_wave_count(&wave, &spawner.settings);
             debug!("Spawning {} alien ships", n_to_spawn);
 
             let r = 10000.;
@@ -92,78 +145,169 @@ pub fn update(
                 0.0,
             );
 
-            let (difficulty_engine_multiplier, difficulty_rotation_multiplier) =
-                match settings.difficulty {
-                    Difficulty::GodMode => (0.75, 0.75),
-                    Difficulty::Easy => (0.75, 0.75),
-                    Difficulty::Normal => (1.0, 1.0),
-                    Difficulty::Hard => (2.0, 1.33),
-                    Difficulty::Impossible => (3.0, 1.5),
-                };
-
-            for _ in 0..n_to_spawn {
+            for i in 0..n_to_spawn {
                 let r = rng.sample(radius_side);
                 let theta = rng.sample(angle_side);
-                let pos = Vec3::new(
+                let pos = Vec2::new(
                     wave_center.x + theta.cos() * r,
                     wave_center.y + theta.sin() * r,
-                    0.0,
                 );
-                let mut cmd: bevy::ecs::system::EntityCommands<'_, '_, '_> = commands.spawn((
-                    AlienShipMarker,
-                    HealthPoints {
-                        max: 50.0,
-                        current: 50.0,
-                    },
-                    Thruster {
-                        max_thrust: ALIEN_SHIP_DRIVE_ENGINE_IMPULSE * difficulty_engine_multiplier,
-                        current_thrust: 0.0,
-                        rampup_rate: 2.0 * difficulty_engine_multiplier,
-                        shutoff_rate: ALIEN_SHIP_DRIVE_ENGINE_IMPULSE
-                            * difficulty_engine_multiplier,
-                        ignition_thrust: ALIEN_SHIP_DRIVE_ENGINE_IMPULSE
-                            * difficulty_engine_multiplier
-                            / 2.0,
-                    },
-                    ShipAi::default(),
-                    OrientationController::new(
-                        ALIEN_SHIP_ROTATION_IMPULSE * difficulty_rotation_multiplier * 0.9,
-                    ),
-                    PositionController::new(
-                        ALIEN_SHIP_DRIVE_ENGINE_IMPULSE * difficulty_engine_multiplier * 0.75,
-                    ), // smaller than max thrust to leave some error margin on slowdown maneuvers
-                    LaserAbility {
-                        last_shot: None,
-                        cooldown: ALIEN_SHIP_LASER_COOLDOWN_S,
-                    },
-                    ComputedTrajectory::default(),
-                    SpriteBundle {
-                        texture: asset_server.load("enemy_ship.png"),
-                        transform: Transform::from_translation(pos),
-                        ..default()
-                    },
-                    ActiveEvents::COLLISION_EVENTS,
-                    AffectedByGravity::default(),
-                    game_layer(),
-                ));
-                cmd.insert((
-                    Ccd::enabled(),
-                    RigidBody::Dynamic,
-                    Collider::ball(32.0),
-                    ColliderMassProperties::Mass(ALIEN_SHIP_MASS),
-                    Damping {
-                        linear_damping: 0.0,
-                        angular_damping: 0.5,
-                    },
-                    Velocity {
-                        linvel: player_velocity.linvel + Vec2::new(rng.gen(), rng.gen()),
-                        ..default()
-                    },
-                ));
-                controller_queue.queue_spawned(cmd.id());
+                let velocity = player_velocity.linvel + Vec2::new(rng.gen(), rng.gen());
+                spawner.spawn(wave_archetype(wave.current_wave, i), pos, velocity);
             }
+            if wave.current_wave.is_multiple_of(BOSS_WAVE_EVERY) {
+                debug!("Spawning a mothership");
+                mothership::spawn(
+                    &mut spawner.commands,
+                    &spawner.asset_server,
+                    wave_center.xy(),
+                    player_velocity.linvel,
+                );
+            }
+            wave_started.send(WaveStarted {
+                wave: wave.current_wave,
+                enemies: n_to_spawn,
+                center: wave_center.xy(),
+            });
             wave.current_wave += 1;
             wave.started_at = Some(time.elapsed_seconds());
         }
     }
 }
+
+pub fn spawn_alien(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    controller_queue: &mut AIControllerQueues,
+    difficulty: Difficulty,
+    archetype: Archetype,
+    pos: Vec2,
+    velocity: Vec2,
+) -> Entity {
+    let (difficulty_engine_multiplier, difficulty_rotation_multiplier) = match difficulty {
+        Difficulty::GodMode => (0.75, 0.75),
+        Difficulty::Easy => (0.75, 0.75),
+        Difficulty::Normal => (1.0, 1.0),
+        Difficulty::Hard => (2.0, 1.33),
+        Difficulty::Impossible => (3.0, 1.5),
+    };
+    let stats = archetype.stats();
+    let engine = ALIEN_SHIP_DRIVE_ENGINE_IMPULSE * difficulty_engine_multiplier * stats.engine;
+    let mut cmd = commands.spawn((
+        AlienShipMarker,
+        archetype,
+        HealthPoints::new(stats.hp),
+        KillCredit::default(),
+        Thruster {
+            max_thrust: engine,
+            current_thrust: 0.0,
+            rampup_rate: 2.0 * difficulty_engine_multiplier,
+            shutoff_rate: engine,
+            ignition_thrust: engine / 2.0,
+        },
+        ShipAi::new(stats.profile),
+        OrientationController::new(
+            ALIEN_SHIP_ROTATION_IMPULSE * difficulty_rotation_multiplier * stats.rotation * 0.9,
+        )
+        .with_angular_inertia(ALIEN_SHIP_ANGULAR_INERTIA * stats.scale * stats.scale),
+        PositionController::new(engine * 0.75), // smaller than max thrust to leave some error margin on slowdown maneuvers
+        LaserAbility {
+            last_shot: None,
+            cooldown: stats.cooldown,
+        },
+        ComputedTrajectory::default(),
+        SpriteBundle {
+            texture: asset_server.load("enemy_ship.png"),
+            sprite: Sprite {
+                color: stats.tint,
+                ..default()
+            },
+            transform: Transform::from_translation(pos.extend(0.0))
+                .with_scale(Vec3::splat(stats.scale)),
+            ..default()
+        },
+        collisions_handler::ship_contact_events(),
+        AffectedByGravity::default(),
+        game_layer(),
+    ));
+    cmd.insert((
+        Ccd::enabled(),
+        RigidBody::Dynamic,
+        Collider::ball(32.0),
+        ColliderMassProperties::Mass(ALIEN_SHIP_MASS),
+        Damping {
+            linear_damping: 0.0,
+            angular_damping: 0.5,
+        },
+        Velocity {
+            linvel: velocity,
+            ..default()
+        },
+    ));
+    if stats.profile.uses(Behavior::Pursue) {
+        cmd.insert(Pursuit::default());
+    }
+    controller_queue.queue_spawned(cmd.id());
+    cmd.id()
+}
+
+type Carrier<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    &'a Archetype,
+    &'a ShipAi,
+    &'a mut LaserAbility,
+);
+
+pub fn launch_drones(
+    mut spawner: AlienSpawner,
+    time: Res<Time>,
+    mut carriers: Query<Carrier>,
+    drones: Query<&Drone>,
+) {
+    for (carrier, t, v, archetype, ai, mut hangar) in carriers.iter_mut() {
+        let Weapon::Hangar { max_drones } = archetype.stats().weapon else {
+            continue;
+        };
+        let launched = drones
+            .iter()
+            .filter(|drone| drone.carrier == carrier)
+            .count();
+        // Drones go out while the carrier engages the player.
+        if launched >= max_drones || !hangar.ready(&time) || ai.behavior != Behavior::Aggro {
+            continue;
+        }
+        let forward = t.up().xy();
+        let drone = spawner.spawn(
+            Archetype::Drone,
+            t.translation.xy() + forward * 80.0 * t.scale.x,
+            v.linvel + forward * DRONE_LAUNCH_SPEED,
+        );
+        spawner.commands.entity(drone).insert(Drone { carrier });
+        hangar.last_shot = Some(time.elapsed_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{wave_archetype, Archetype};
+
+    #[test]
+    fn waves_mix_in_archetypes_as_they_go() {
+        assert!((0..20).all(|i| wave_archetype(1, i) == Archetype::Raider));
+        let fifth: Vec<_> = (0..100).map(|i| wave_archetype(5, i)).collect();
+        for archetype in [
+            Archetype::Raider,
+            Archetype::Veteran,
+            Archetype::Kamikaze,
+            Archetype::Sniper,
+            Archetype::Bomber,
+            Archetype::Carrier,
+        ] {
+            assert!(fifth.contains(&archetype), "{:?}", archetype);
+        }
+        // Drones only come out of carriers.
+        assert!(!fifth.contains(&Archetype::Drone));
+    }
+}
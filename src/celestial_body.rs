@@ -1,19 +1,27 @@
 use std::f32::consts::PI;
 
-use bevy::prelude::*;
+use bevy::{asset::LoadState, prelude::*};
 use bevy_rapier2d::{
     dynamics::{Ccd, RigidBody},
     geometry::{ActiveEvents, Collider, ColliderMassProperties, Restitution},
 };
+use serde::Deserialize;
 
-use crate::{camera::game_layer, gravity::AttractingBody};
+use crate::{
+    camera::game_layer,
+    gravity::AttractingBody,
+    star_system::{BodyDef, OrbitingBodyDef, StarSystemDef, StarSystemHandle},
+    AppState,
+};
 
-const SYSTEM_DISTANCE_SCALE: f32 = 10000.0;
+const KEPLER_SOLVER_MAX_ITERATIONS: u32 = 16;
+const KEPLER_SOLVER_TOLERANCE: f32 = 1e-6;
+const VELOCITY_ESTIMATE_DT: f32 = 0.1;
 
 #[derive(Component)]
 pub struct CelestialBodyMarker;
 
-#[derive(Component)]
+#[derive(Component, Deserialize, Clone, Debug)]
 pub struct StarterPlanetMarker {
     pub orbit_radius: f32,
     pub theta: f32,
@@ -22,7 +30,52 @@ pub struct StarterPlanetMarker {
 
 #[derive(Resource)]
 
-pub struct OrbitHierarchy(#[allow(dead_code)] Vec<OrbitHierarchyNode>);
+pub struct OrbitHierarchy(Vec<OrbitHierarchyNode>);
+
+impl OrbitHierarchy {
+    pub fn update(&mut self, dt: f32) {
+        fn update_node(node: &mut OrbitHierarchyNode, dt: f32) {
+            node.dynamics.update(dt);
+            for child in node.children.iter_mut() {
+                update_node(child, dt);
+            }
+        }
+        for root in self.0.iter_mut() {
+            update_node(root, dt);
+        }
+    }
+
+    // Every orbit of the hierarchy, with the current position of the parent it goes around.
+    // Empty barycenters are included, so binary pairs get their shared orbit drawn too.
+    pub fn orbits(&self) -> Vec<(Vec2, OrbitDef)> {
+        fn collect(node: &OrbitHierarchyNode, orbits: &mut Vec<(Vec2, OrbitDef)>) {
+            let pos = node.dynamics.pos(0.0);
+            for child in node.children.iter() {
+                if let Some(orbit) = child.dynamics.chain.last() {
+                    orbits.push((pos, *orbit));
+                }
+                collect(child, orbits);
+            }
+        }
+        let mut orbits = vec![];
+        for root in self.0.iter() {
+            collect(root, &mut orbits);
+        }
+        orbits
+    }
+
+    pub fn shift(&mut self, delta: Vec2) {
+        fn shift_node(node: &mut OrbitHierarchyNode, delta: Vec2) {
+            node.dynamics.origin += delta;
+            for child in node.children.iter_mut() {
+                shift_node(child, delta);
+            }
+        }
+        for root in self.0.iter_mut() {
+            shift_node(root, delta);
+        }
+    }
+}
 
 pub struct OrbitHierarchyNode {
     pub dynamics: CircularOrbitChain,
@@ -40,7 +93,7 @@ impl OrbitHierarchyNode {
         }
     }
 
-    pub fn with_child(&mut self, orbit: CircularOrbitDef) -> &mut OrbitHierarchyNode {
+    pub fn with_child(&mut self, orbit: OrbitDef) -> &mut OrbitHierarchyNode {
         let mut chain = self.dynamics.chain.clone();
         chain.push(orbit);
         let child = OrbitHierarchyNode {
@@ -55,171 +108,243 @@ impl OrbitHierarchyNode {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Copy, Clone, Deserialize, Debug)]
 pub struct CircularOrbitDef {
-    theta: f32,
-    radius: f32,
-    freq: f32,
+    pub theta: f32,
+    pub radius: f32,
+    pub freq: f32,
+}
+
+impl CircularOrbitDef {
+    pub fn offset(&self, dt: f32) -> Vec2 {
+        Vec2 {
+            x: (self.theta + dt * self.freq).cos() * self.radius,
+            y: (self.theta + dt * self.freq).sin() * self.radius,
+        }
+    }
+}
+
+// An elliptical orbit around the parent, with the parent sitting at one of the ellipse's foci.
+// `freq` is the mean motion: how fast the mean anomaly grows, in radians per second.
+#[derive(Copy, Clone, Deserialize, Debug)]
+pub struct KeplerianOrbitDef {
+    pub eccentricity: f32,
+    pub semi_major_axis: f32,
+    pub argument_of_periapsis: f32,
+    pub mean_anomaly: f32,
+    pub freq: f32,
+}
+
+impl KeplerianOrbitDef {
+    pub fn offset(&self, dt: f32) -> Vec2 {
+        let e = self.eccentricity;
+        let a = self.semi_major_axis;
+        let mean_anomaly = (self.mean_anomaly + dt * self.freq).rem_euclid(PI * 2.0);
+        let eccentric_anomaly = solve_kepler_equation(mean_anomaly, e);
+        let perifocal = Vec2 {
+            x: a * (eccentric_anomaly.cos() - e),
+            y: a * (1.0 - e * e).sqrt() * eccentric_anomaly.sin(),
+        };
+        Vec2::from_angle(self.argument_of_periapsis).rotate(perifocal)
+    }
+}
+
+// Solves Kepler's equation M = E - e * sin(E) for the eccentric anomaly E using Newton's method.
+pub fn solve_kepler_equation(mean_anomaly: f32, eccentricity: f32) -> f32 {
+    // Starting from M converges quickly for most orbits, but can overshoot on very eccentric ones.
+    let mut eccentric_anomaly = if eccentricity < 0.8 { mean_anomaly } else { PI };
+    for _ in 0..KEPLER_SOLVER_MAX_ITERATIONS {
+        let delta = (eccentric_anomaly - eccentricity * eccentric_anomaly.sin() - mean_anomaly)
+            / (1.0 - eccentricity * eccentric_anomaly.cos());
+        eccentric_anomaly -= delta;
+        if delta.abs() < KEPLER_SOLVER_TOLERANCE {
+            break;
+        }
+    }
+    eccentric_anomaly
+}
+
+// Star system files pick the orbit type from the fields that are set.
+#[derive(Copy, Clone, Deserialize, Debug)]
+#[serde(untagged)]
+pub enum OrbitDef {
+    Circular(CircularOrbitDef),
+    Keplerian(KeplerianOrbitDef),
+}
+
+impl OrbitDef {
+    pub fn update(&mut self, dt: f32) {
+        match self {
+            OrbitDef::Circular(orbit) => {
+                orbit.theta = (orbit.theta + (dt * orbit.freq)) % (PI * 2.0);
+            }
+            OrbitDef::Keplerian(orbit) => {
+                orbit.mean_anomaly = (orbit.mean_anomaly + (dt * orbit.freq)) % (PI * 2.0);
+            }
+        }
+    }
+
+    pub fn offset(&self, dt: f32) -> Vec2 {
+        match self {
+            OrbitDef::Circular(orbit) => orbit.offset(dt),
+            OrbitDef::Keplerian(orbit) => orbit.offset(dt),
+        }
+    }
+
+    // `segments + 1` points along the whole orbit, relative to the parent. The first and last points are the same.
+    pub fn path(&self, segments: usize) -> Vec<Vec2> {
+        (0..=segments)
+            .map(|i| {
+                let phase = i as f32 / segments as f32 * PI * 2.0;
+                match self {
+                    OrbitDef::Circular(orbit) => CircularOrbitDef {
+                        theta: orbit.theta + phase,
+                        ..*orbit
+                    }
+                    .offset(0.0),
+                    OrbitDef::Keplerian(orbit) => KeplerianOrbitDef {
+                        mean_anomaly: orbit.mean_anomaly + phase,
+                        ..*orbit
+                    }
+                    .offset(0.0),
+                }
+            })
+            .collect()
+    }
+
+    // The closest and farthest distances to the parent.
+    pub fn apsides(&self) -> (f32, f32) {
+        match self {
+            OrbitDef::Circular(orbit) => (orbit.radius, orbit.radius),
+            OrbitDef::Keplerian(orbit) => (
+                orbit.semi_major_axis * (1.0 - orbit.eccentricity),
+                orbit.semi_major_axis * (1.0 + orbit.eccentricity),
+            ),
+        }
+    }
+}
+
+impl From<CircularOrbitDef> for OrbitDef {
+    fn from(value: CircularOrbitDef) -> Self {
+        OrbitDef::Circular(value)
+    }
+}
+
+impl From<KeplerianOrbitDef> for OrbitDef {
+    fn from(value: KeplerianOrbitDef) -> Self {
+        OrbitDef::Keplerian(value)
+    }
 }
 
 #[derive(Component, Clone)]
 pub struct CircularOrbitChain {
     pub origin: Vec2,
-    pub chain: Vec<CircularOrbitDef>,
+    pub chain: Vec<OrbitDef>,
 }
 
 impl CircularOrbitChain {
     pub fn update(&mut self, dt: f32) {
         for orbit in self.chain.iter_mut() {
-            orbit.theta = (orbit.theta + (dt * orbit.freq)) % (PI * 2.0);
+            orbit.update(dt);
         }
     }
 
     pub fn pos(&self, dt: f32) -> Vec2 {
-        self.chain.iter().fold(
-            self.origin,
-            |pos,
-             CircularOrbitDef {
-                 radius,
-                 theta,
-                 freq,
-             }| {
-                pos + Vec2 {
-                    x: (theta + dt * freq).cos() * radius,
-                    y: (theta + dt * freq).sin() * radius,
-                }
-            },
-        )
+        self.chain
+            .iter()
+            .fold(self.origin, |pos, orbit| pos + orbit.offset(dt))
+    }
+
+    // Bodies move on rails, we differentiate their position.
+    pub fn velocity(&self) -> Vec2 {
+        (self.pos(VELOCITY_ESTIMATE_DT) - self.pos(-VELOCITY_ESTIMATE_DT))
+            / (2.0 * VELOCITY_ESTIMATE_DT)
+    }
+
+    // The velocity of what the body revolves around, e.g. a moon's planet.
+    pub fn parent_velocity(&self) -> Vec2 {
+        let parent_pos = |dt: f32| {
+            self.chain[..self.chain.len().saturating_sub(1)]
+                .iter()
+                .fold(self.origin, |pos, orbit| pos + orbit.offset(dt))
+        };
+        (parent_pos(VELOCITY_ESTIMATE_DT) - parent_pos(-VELOCITY_ESTIMATE_DT))
+            / (2.0 * VELOCITY_ESTIMATE_DT)
     }
 }
 
-pub fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
-    // This is this environment generation.
-    let sprite_radius = 500.0;
-
-    let mut parent_saturn = OrbitHierarchyNode::start(Vec2 {
-        x: -10000.0,
-        y: -10000.0,
-    });
-    commands.spawn(gen_body_bundle(
-        &parent_saturn,
-        5.0,
-        asset_server.load("planet_big.webp"),
-        sprite_radius,
-        5e7,
-    ));
-
-    let mut parent_massive_pink_hole = OrbitHierarchyNode::start(Vec2 {
-        x: 0.0,
-        y: 100000.0,
-    });
-    commands.spawn(gen_body_bundle(
-        &parent_massive_pink_hole,
-        3.0,
-        asset_server.load("planet_pink_shiny_rock.webp"),
-        sprite_radius,
-        7e7,
-    ));
-
-    let volcanic = parent_massive_pink_hole.with_child(CircularOrbitDef {
-        theta: -PI / 2.0,
-        radius: 50000.0,
-        freq: 1.0 / 30.0,
-    });
-    commands.spawn(gen_body_bundle(
-        &volcanic,
-        5.0,
-        asset_server.load("planet_big_1.webp"),
-        sprite_radius,
-        5e7,
-    ));
-
-    let parent_pink_rock = OrbitHierarchyNode::start(Vec2 {
-        x: 70000.0,
-        y: -70000.0,
-    });
-    commands.spawn(gen_body_bundle(
-        &parent_pink_rock,
-        5.0,
-        asset_server.load("planet_pink_shiny_rock.webp"),
-        sprite_radius,
-        5e7,
-    ));
-
-    {
-        let child_moon_node = parent_saturn.with_child(CircularOrbitDef {
-            theta: 0.0,
-            radius: 4.0 * SYSTEM_DISTANCE_SCALE,
-            freq: 1.0 / 300.0,
-        });
-        commands.spawn(gen_body_bundle(
-            child_moon_node,
-            1.5,
-            asset_server.load("planet_small_0.webp"),
-            sprite_radius,
-            1e7,
-        ));
+pub fn setup(
+    mut commands: Commands,
+    mut next_state: ResMut<NextState<AppState>>,
+    asset_server: Res<AssetServer>,
+    star_system: Res<StarSystemHandle>,
+    star_systems: Res<Assets<StarSystemDef>>,
+    bodies: Query<(), With<CelestialBodyMarker>>,
+) {
+    if !bodies.is_empty() {
+        return;
+    }
+    if let Some(LoadState::Failed) = asset_server.get_load_state(&star_system.0) {
+        error!("The star system could not be loaded, going back to the menu.");
+        next_state.set(AppState::Menu);
+        return;
+    }
+    // The star system may still be loading. We'll try again next frame.
+    if let Some(system) = star_systems.get(&star_system.0) {
+        let hierarchy = system
+            .roots
+            .iter()
+            .map(|root| {
+                let mut node = OrbitHierarchyNode::start(root.position);
+                spawn_bodies(
+                    &mut commands,
+                    &asset_server,
+                    &mut node,
+                    &root.name,
+                    &root.body,
+                    &root.children,
+                );
+                node
+            })
+            .collect();
+        commands.insert_resource(OrbitHierarchy(hierarchy));
+    }
+}
 
-        let child_child_moon_node = child_moon_node.with_child(CircularOrbitDef {
-            theta: 0.0,
-            radius: 1.0 * SYSTEM_DISTANCE_SCALE,
-            freq: 1.0 / 40.0,
-        });
-        commands.spawn((
+fn spawn_bodies(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    node: &mut OrbitHierarchyNode,
+    name: &str,
+    body: &Option<BodyDef>,
+    children: &[OrbitingBodyDef],
+) {
+    if let Some(body) = body {
+        let mut cmd = commands.spawn((
+            Name::new(name.to_string()),
             gen_body_bundle(
-                child_child_moon_node,
-                0.5,
-                asset_server.load("planet_small_3.webp"),
-                sprite_radius,
-                4e6,
+                node,
+                body.scale,
+                asset_server.load(&body.sprite),
+                body.radius,
+                body.mass,
             ),
-            StarterPlanetMarker {
-                orbit_radius: 1500.0,
-                theta: PI / 2.0,
-                velocity: -Vec2::X * 1000.0,
-            },
-        ));
-    }
-
-    let child_binary_center = parent_saturn.with_child(CircularOrbitDef {
-        theta: PI,
-        radius: 8.0 * SYSTEM_DISTANCE_SCALE,
-        freq: 1.0 / 600.0,
-    });
-    {
-        let binary_a = child_binary_center.with_child(CircularOrbitDef {
-            theta: 0.0,
-            radius: 0.08 * SYSTEM_DISTANCE_SCALE,
-            freq: 1.0,
-        });
-        commands.spawn(gen_body_bundle(
-            binary_a,
-            0.5,
-            asset_server.load("planet_blue_shiny_rock.webp"),
-            sprite_radius,
-            3e6,
         ));
+        if let Some(starter) = &body.starter {
+            cmd.insert(starter.clone());
+        }
     }
-    {
-        let binary_b = child_binary_center.with_child(CircularOrbitDef {
-            theta: PI,
-            radius: 0.08 * SYSTEM_DISTANCE_SCALE,
-            freq: 1.0,
-        });
-        commands.spawn(gen_body_bundle(
-            binary_b,
-            0.5,
-            asset_server.load("planet_small_1.webp"),
-            sprite_radius,
-            3e6,
-        ));
+    for child in children.iter() {
+        let child_node = node.with_child(child.orbit);
+        spawn_bodies(
+            commands,
+            asset_server,
+            child_node,
+            &child.name,
+            &child.body,
+            &child.children,
+        );
     }
-
-    commands.insert_resource(OrbitHierarchy(vec![
-        parent_saturn,
-        parent_massive_pink_hole,
-        parent_pink_rock,
-    ]));
 }
 
 fn gen_body_bundle(
@@ -250,8 +375,12 @@ fn gen_body_bundle(
 
 pub fn update(
     time: Res<Time>,
+    hierarchy: Option<ResMut<OrbitHierarchy>>,
     mut bodies: Query<(&mut Transform, &mut CircularOrbitChain), With<CelestialBodyMarker>>,
 ) {
+    if let Some(mut hierarchy) = hierarchy {
+        hierarchy.update(time.delta_seconds());
+    }
     for (_, mut orbit) in bodies.iter_mut() {
         orbit.update(time.delta_seconds());
     }
@@ -264,4 +393,145 @@ pub fn cleanup(mut commands: Commands, bodies: Query<Entity, With<CelestialBodyM
     for entity in bodies.iter() {
         commands.entity(entity).despawn_recursive();
     }
+    commands.remove_resource::<OrbitHierarchy>();
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::PI;
+
+    use bevy::math::Vec2;
+
+    use super::{
+        solve_kepler_equation, CircularOrbitChain, CircularOrbitDef, KeplerianOrbitDef, OrbitDef,
+        OrbitHierarchy, OrbitHierarchyNode,
+    };
+
+    const EPSILON: f32 = 1e-2;
+
+    fn comet(
+        eccentricity: f32,
+        argument_of_periapsis: f32,
+        mean_anomaly: f32,
+    ) -> KeplerianOrbitDef {
+        KeplerianOrbitDef {
+            eccentricity,
+            semi_major_axis: 1000.0,
+            argument_of_periapsis,
+            mean_anomaly,
+            freq: 0.1,
+        }
+    }
+
+    fn assert_close(a: Vec2, b: Vec2) {
+        assert!(a.distance(b) < EPSILON, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn kepler_equation_is_satisfied() {
+        for e in [0.0, 0.1, 0.5, 0.9, 0.99] {
+            for i in 0..32 {
+                let m = i as f32 / 32.0 * 2.0 * PI;
+                let ea = solve_kepler_equation(m, e);
+                assert!((ea - e * ea.sin() - m).abs() < 1e-5, "e={} m={}", e, m);
+            }
+        }
+    }
+
+    #[test]
+    fn keplerian_periapsis_and_apoapsis() {
+        let orbit = comet(0.6, 0.0, 0.0);
+        assert_close(orbit.offset(0.0), Vec2::new(400.0, 0.0));
+        let orbit = comet(0.6, 0.0, PI);
+        assert_close(orbit.offset(0.0), Vec2::new(-1600.0, 0.0));
+    }
+
+    #[test]
+    fn keplerian_at_quarter_eccentric_anomaly() {
+        // For E = pi/2, M = pi/2 - e and the body sits at (-a * e, b).
+        let e = 0.5;
+        let orbit = comet(e, 0.0, PI / 2.0 - e);
+        let b = 1000.0 * (1.0 - e * e).sqrt();
+        assert_close(orbit.offset(0.0), Vec2::new(-500.0, b));
+    }
+
+    #[test]
+    fn keplerian_argument_of_periapsis_rotates_the_ellipse() {
+        let orbit = comet(0.6, PI / 2.0, 0.0);
+        assert_close(orbit.offset(0.0), Vec2::new(0.0, 400.0));
+    }
+
+    #[test]
+    fn keplerian_without_eccentricity_is_circular() {
+        let keplerian = comet(0.0, 0.3, 1.2);
+        let circular = CircularOrbitDef {
+            theta: 1.5,
+            radius: 1000.0,
+            freq: 0.1,
+        };
+        for dt in [0.0, 1.0, 10.0, 100.0] {
+            assert_close(keplerian.offset(dt), circular.offset(dt));
+        }
+    }
+
+    #[test]
+    fn chain_update_matches_pos_lookahead() {
+        let mut chain = CircularOrbitChain {
+            origin: Vec2::new(100.0, -100.0),
+            chain: vec![
+                OrbitDef::Circular(CircularOrbitDef {
+                    theta: 0.0,
+                    radius: 5000.0,
+                    freq: 0.01,
+                }),
+                OrbitDef::Keplerian(comet(0.7, 1.0, 0.5)),
+            ],
+        };
+        let expected = chain.pos(12.5);
+        for _ in 0..5 {
+            chain.update(2.5);
+        }
+        assert_close(chain.pos(0.0), expected);
+    }
+
+    #[test]
+    fn orbit_path_goes_all_the_way_around() {
+        let circular = OrbitDef::Circular(CircularOrbitDef {
+            theta: 0.5,
+            radius: 1000.0,
+            freq: 0.1,
+        });
+        let path = circular.path(64);
+        assert_eq!(path.len(), 65);
+        assert_close(path[0], circular.offset(0.0));
+        assert_close(path[64], path[0]);
+        assert!(path.iter().all(|p| (p.length() - 1000.0).abs() < EPSILON));
+
+        let keplerian = OrbitDef::Keplerian(comet(0.6, 0.0, 0.0));
+        let path = keplerian.path(64);
+        assert_close(path[0], Vec2::new(400.0, 0.0));
+        assert_close(path[32], Vec2::new(-1600.0, 0.0));
+    }
+
+    #[test]
+    fn hierarchy_orbits_follow_their_parents() {
+        let orbit = |radius: f32| CircularOrbitDef {
+            theta: 0.0,
+            radius,
+            freq: 0.1,
+        };
+        let mut root = OrbitHierarchyNode::start(Vec2::new(10.0, 0.0));
+        let barycenter = root.with_child(orbit(1000.0).into());
+        barycenter.with_child(orbit(50.0).into());
+        barycenter.with_child(orbit(50.0).into());
+        let mut hierarchy = OrbitHierarchy(vec![root]);
+        hierarchy.update(5.0);
+
+        let orbits = hierarchy.orbits();
+        assert_eq!(orbits.len(), 3);
+        assert_close(orbits[0].0, Vec2::new(10.0, 0.0));
+        let barycenter_pos = Vec2::new(10.0, 0.0) + orbit(1000.0).offset(5.0);
+        assert_close(orbits[1].0, barycenter_pos);
+        assert_close(orbits[2].0, barycenter_pos);
+    }
 }
@@ -1,182 +1,585 @@
-use std::f32::consts::PI;
-
-use bevy::prelude::*;
-use bevy_rapier2d::{dynamics::Velocity, pipeline::CollisionEvent};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use bevy_rapier2d::{
+    dynamics::Velocity,
+    geometry::{ActiveEvents, ColliderMassProperties, ContactForceEventThreshold},
+    pipeline::{CollisionEvent, ContactForceEvent},
+    plugin::{PhysicsSet, RapierContext},
+};
 
 use crate::{
     alien_ship::AlienShipMarker,
     celestial_body::CelestialBodyMarker,
+    death::KillCredit,
     despawn_queue::DespawnQueue,
-    gravity::AffectedByGravity,
-    healthpoints::HealthPoints,
+    gameplay_events::{BodyImpact, PlayerDamaged},
+    healthpoints::{DamageCause, HealthPoints, Hit},
     impulses_aggregator::AddExternalImpulse,
+    landing::Landed,
     lasers::{Laser, LaserOrigin},
-    player::{PlayerMarker, PLAYER_MASS},
-    thruster::Thruster,
+    mothership::{Hardpoint, HardpointKind, Mothership},
+    player::PlayerMarker,
+    simulation::{self, SimulationMode},
     ui::{Difficulty, GameSettings},
-    GLOBAL_IMPULSE_DURATION_MULT,
+    AppState, GLOBAL_IMPULSE_DURATION_MULT,
 };
 
 const LASER_HIT_LINEAR_IMPULSE: f32 = 1.0 * GLOBAL_IMPULSE_DURATION_MULT;
 const LASER_HIT_ANGULAR_IMPULSE: f32 = 150.0 * GLOBAL_IMPULSE_DURATION_MULT;
+// Ships take damage from the kinetic energy a contact's impulse puts into them,
+// so light ships suffer more from the same hit than heavy ones.
+const IMPACT_ENERGY_PER_HP: f32 = 10000.0;
+// Below this change of velocity, contacts are scrapes that don't hurt.
+const IMPACT_MIN_DELTA_V: f32 = 50.0;
+const SHIP_CONTACT_FORCE_THRESHOLD: f32 = 1000.0;
 
-pub fn update(
-    mut despawn_queue: ResMut<DespawnQueue>,
+// Rapier's events, collected right after the step that produced them,
+// so that routing doesn't depend on how many frames go by between ticks.
+#[derive(Resource, Default)]
+pub struct PhysicsEvents {
+    collisions: Vec<CollisionEvent>,
+    contact_impulses: Vec<(Entity, Entity, f32)>, // over the step
+}
+
+// Lasers are sensors: they only report the start of their collisions.
+#[derive(Event)]
+pub struct LaserHitShip {
+    pub laser: Entity,
+    pub ship: Entity,
+}
+
+#[derive(Event)]
+pub struct LaserHitHardpoint {
+    pub laser: Entity,
+    pub hardpoint: Entity,
+}
+
+#[derive(Event)]
+pub struct LaserHitBody {
+    pub laser: Entity,
+}
+
+// Solid contacts are reported every frame they push hard enough, with the frame's total impulse.
+#[derive(Event)]
+pub struct ShipHitBody {
+    pub ship: Entity,
+    pub body: Entity,
+    pub impulse: f32,
+}
+
+#[derive(Event)]
+pub struct ShipRammedShip {
+    pub ships: [Entity; 2],
+    pub impulse: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum CollidingKind {
+    Laser,
+    Ship,
+    Hardpoint,
+    Body,
+}
+
+#[derive(PartialEq, Eq, Debug)]
+pub enum CollisionPair {
+    LaserShip { laser: Entity, ship: Entity },
+    LaserHardpoint { laser: Entity, hardpoint: Entity },
+    LaserBody { laser: Entity, body: Entity },
+    ShipBody { ship: Entity, body: Entity },
+    ShipShip([Entity; 2]),
+}
+
+// Both orderings of a pair end up the same.
+pub fn classify(a: (Entity, CollidingKind), b: (Entity, CollidingKind)) -> Option<CollisionPair> {
+    let ((a, kind_a), (b, kind_b)) = if a.1 <= b.1 { (a, b) } else { (b, a) };
+    match (kind_a, kind_b) {
+        (CollidingKind::Laser, CollidingKind::Ship) => {
+            Some(CollisionPair::LaserShip { laser: a, ship: b })
+        }
+        (CollidingKind::Laser, CollidingKind::Hardpoint) => Some(CollisionPair::LaserHardpoint {
+            laser: a,
+            hardpoint: b,
+        }),
+        (CollidingKind::Laser, CollidingKind::Body) => {
+            Some(CollisionPair::LaserBody { laser: a, body: b })
+        }
+        (CollidingKind::Ship, CollidingKind::Body) => {
+            Some(CollisionPair::ShipBody { ship: a, body: b })
+        }
+        (CollidingKind::Ship, CollidingKind::Ship) => Some(CollisionPair::ShipShip([a, b])),
+        _ => None,
+    }
+}
+
+type CollidingKinds<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Has<Laser>,
+        Has<PlayerMarker>,
+        Has<AlienShipMarker>,
+        Has<CelestialBodyMarker>,
+        Has<Hardpoint>,
+        Option<&'static Parent>,
+    ),
+>;
+
+type LaserTargets<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Velocity,
+        &'static ColliderMassProperties,
+        &'static mut HealthPoints,
+        Option<&'static mut KillCredit>,
+        Option<&'static Mothership>,
+        Has<PlayerMarker>,
+    ),
+>;
+
+type Ships<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Velocity,
+        &'static mut HealthPoints,
+        &'static ColliderMassProperties,
+        Option<&'static mut KillCredit>,
+        Has<PlayerMarker>,
+    ),
+    Without<Landed>,
+>;
+
+// Where `route_collisions` sends each kind of collision.
+#[derive(SystemParam)]
+pub struct RoutedCollisions<'w> {
+    laser_hit_ship: EventWriter<'w, LaserHitShip>,
+    laser_hit_hardpoint: EventWriter<'w, LaserHitHardpoint>,
+    laser_hit_body: EventWriter<'w, LaserHitBody>,
+    ship_hit_body: EventWriter<'w, ShipHitBody>,
+    ship_rammed_ship: EventWriter<'w, ShipRammedShip>,
+}
+
+// What a laser hitting a ship does besides damage.
+#[derive(SystemParam)]
+pub struct LaserHitEffects<'w> {
+    impulses: EventWriter<'w, AddExternalImpulse>,
+    player_damaged: EventWriter<'w, PlayerDamaged>,
+}
+
+pub fn setup(app: &mut App) {
+    let physics_schedule = app.world.resource::<SimulationMode>().physics_schedule();
+    app.insert_resource(PhysicsEvents::default());
+    app.add_systems(
+        physics_schedule,
+        collect_physics_events
+            .after(PhysicsSet::Writeback)
+            .run_if(in_state(AppState::Game)),
+    );
+    app.add_event::<LaserHitShip>();
+    app.add_event::<LaserHitHardpoint>();
+    app.add_event::<LaserHitBody>();
+    app.add_event::<ShipHitBody>();
+    app.add_event::<ShipRammedShip>();
+}
+
+// Ships report the contact forces they're subject to, for impact damage.
+pub fn ship_contact_events() -> (ActiveEvents, ContactForceEventThreshold) {
+    (
+        ActiveEvents::COLLISION_EVENTS | ActiveEvents::CONTACT_FORCE_EVENTS,
+        ContactForceEventThreshold(SHIP_CONTACT_FORCE_THRESHOLD),
+    )
+}
+
+pub fn impact_damage(impulse: f32, mass: f32) -> f32 {
+    let delta_v = impulse / mass;
+    if delta_v < IMPACT_MIN_DELTA_V {
+        return 0.0;
+    }
+    0.5 * mass * delta_v * delta_v / IMPACT_ENERGY_PER_HP
+}
+
+pub fn collect_physics_events(
+    context: Res<RapierContext>,
     mut collisions: EventReader<CollisionEvent>,
-    mut player: Query<
-        (Entity, &Transform, &Velocity, &mut HealthPoints),
-        (With<PlayerMarker>, Without<AlienShipMarker>),
-    >,
-    mut alien_ships: Query<
-        (Entity, &mut HealthPoints, &Transform, &Velocity),
-        (With<AlienShipMarker>, Without<PlayerMarker>),
-    >,
-    mut lasers: Query<(Entity, &Transform, &Velocity, &mut Laser)>,
-    celestial_bodies: Query<Entity, With<CelestialBodyMarker>>,
-    mut impulses: EventWriter<AddExternalImpulse>,
-    player_movement_query: Query<(&AffectedByGravity, &Thruster, &Transform)>,
-    settings: Res<GameSettings>,
+    mut contacts: EventReader<ContactForceEvent>,
+    mut events: ResMut<PhysicsEvents>,
 ) {
-    for event in collisions.read() {
-        if let &CollisionEvent::Started(a, b, _contact) = event {
-            // debug!("Collision detected between {:?} and {:?}", a, b);
-
-            // Check for an alien laser hitting the player
-            if let Some(((e, pt, pv, mut player_hp), (laser_entity, lt, lv, mut laser))) =
-                if let (Ok(p), Ok(l)) = (player.get_mut(a), lasers.get_mut(b)) {
-                    Some((p, l))
-                } else if let (Ok(p), Ok(l)) = (player.get_mut(b), lasers.get_mut(a)) {
-                    Some((p, l))
-                } else {
-                    None
-                }
-            {
-                if laser.origin == LaserOrigin::Enemy {
-                    // debug!("Player's been hit by enemy");
-                    despawn_queue.1.insert(laser_entity);
-                    player_hp.decrease(laser.damage, settings.difficulty);
-                    laser.damage = 0.0;
-
-                    // Compute linear and angular knockback
-                    let dv = pv.linvel - lv.linvel;
-                    let dp = pt.translation.xy() - lt.translation.xy();
-                    let dot = -dv.normalize_or_zero().dot(dp.normalize_or_zero());
-                    let perp_dot = -dv.normalize_or_zero().perp_dot(dp.normalize_or_zero());
-                    let linear =
-                        dp.normalize_or_zero() * LASER_HIT_LINEAR_IMPULSE * dot * PLAYER_MASS;
-                    let angular = LASER_HIT_ANGULAR_IMPULSE * perp_dot * PLAYER_MASS;
-                    impulses.send(AddExternalImpulse {
-                        entity: e,
-                        impulse: linear,
-                        torque_impulse: angular,
-                    })
-                }
+    events.collisions.extend(collisions.read().copied());
+    // The forces are averaged over the substep they were reported in, with the step's own `dt`:
+    // the frame's delta time is off after a hitch.
+    let substep_dt = simulation::physics_substep_dt(context.integration_parameters.dt);
+    events.contact_impulses.extend(contacts.read().map(|event| {
+        (
+            event.collider1,
+            event.collider2,
+            event.total_force_magnitude * substep_dt,
+        )
+    }));
+}
+
+// Of two ships in contact, given their positions and velocities, the one that closes in faster
+// along the line between them.
+pub fn rammer(a: (Entity, Vec2, Vec2), b: (Entity, Vec2, Vec2)) -> Entity {
+    let normal = (b.1 - a.1).normalize_or_zero();
+    if a.2.dot(normal) >= -b.2.dot(normal) {
+        a.0
+    } else {
+        b.0
+    }
+}
+
+// Turns Rapier's events into the typed events below, that separate systems handle.
+pub fn route_collisions(
+    mut events: ResMut<PhysicsEvents>,
+    kinds: CollidingKinds,
+    mut routed: RoutedCollisions,
+) {
+    let kind = |entity: Entity| {
+        let (laser, player, alien, body, hardpoint, _) = kinds.get(entity).ok()?;
+        if laser {
+            Some((entity, CollidingKind::Laser))
+        } else if player || alien {
+            Some((entity, CollidingKind::Ship))
+        } else if hardpoint {
+            Some((entity, CollidingKind::Hardpoint))
+        } else if body {
+            Some((entity, CollidingKind::Body))
+        } else {
+            None
+        }
+    };
+    let pair = |a: Entity, b: Entity| classify(kind(a)?, kind(b)?);
+    // Hardpoints are part of their mothership's hull: it takes the blows they get.
+    let hull = |entity: Entity| match kinds.get(entity) {
+        Ok((.., true, Some(parent))) => parent.get(),
+        _ => entity,
+    };
+
+    for event in events.collisions.drain(..) {
+        let CollisionEvent::Started(a, b, _) = event else {
+            continue;
+        };
+        match pair(a, b) {
+            Some(CollisionPair::LaserShip { laser, ship }) => {
+                routed.laser_hit_ship.send(LaserHitShip { laser, ship })
+            }
+            Some(CollisionPair::LaserHardpoint { laser, hardpoint }) => routed
+                .laser_hit_hardpoint
+                .send(LaserHitHardpoint { laser, hardpoint }),
+            Some(CollisionPair::LaserBody { laser, .. }) => {
+                routed.laser_hit_body.send(LaserHitBody { laser })
             }
+            _ => {}
+        }
+    }
 
-            // Check for any laser hitting an alien ship
-            if let Some(((e, mut alien_ship, at, av), (laser_entity, lt, lv, laser))) =
-                if let (Ok(s), Ok(l)) = (alien_ships.get_mut(a), lasers.get(b)) {
-                    Some((s, l))
-                } else if let (Ok(s), Ok(l)) = (alien_ships.get_mut(b), lasers.get(a)) {
-                    Some((s, l))
-                } else {
-                    None
-                }
-            {
-                // debug!("An alien ship has been hit");
-                despawn_queue.1.insert(laser_entity);
-                alien_ship.decrease(laser.damage, Difficulty::Normal);
-
-                // Compute linear and angular knockback
-                let dv = av.linvel - lv.linvel;
-                let dp = at.translation.xy() - lt.translation.xy();
-                let dot = -dv.normalize_or_zero().dot(dp.normalize_or_zero());
-                let perp_dot = -dv.normalize_or_zero().perp_dot(dp.normalize());
-                let linear = dp.normalize_or_zero() * LASER_HIT_LINEAR_IMPULSE * dot;
-                let angular = LASER_HIT_ANGULAR_IMPULSE * perp_dot;
-                impulses.send(AddExternalImpulse {
-                    entity: e,
-                    impulse: linear,
-                    torque_impulse: angular,
+    // A pair can touch several times in a frame, its impulses add up.
+    let mut impulses: HashMap<(Entity, Entity), f32> = HashMap::new();
+    for (a, b, impulse) in events.contact_impulses.drain(..) {
+        let (a, b) = if a < b { (a, b) } else { (b, a) };
+        *impulses.entry((a, b)).or_default() += impulse;
+    }
+    for ((a, b), impulse) in impulses {
+        match pair(hull(a), hull(b)) {
+            Some(CollisionPair::ShipBody { ship, body }) => {
+                routed.ship_hit_body.send(ShipHitBody {
+                    ship,
+                    body,
+                    impulse,
                 })
             }
+            Some(CollisionPair::ShipShip(ships)) => routed
+                .ship_rammed_ship
+                .send(ShipRammedShip { ships, impulse }),
+            _ => {}
+        }
+    }
+}
 
-            // Check for player ship hitting alien ship
-            if let Some(((_e, mut alien_ship, _, _), _)) =
-                if let (Ok(s), Ok(p)) = (alien_ships.get_mut(a), player.get(b)) {
-                    Some((s, p))
-                } else if let (Ok(s), Ok(p)) = (alien_ships.get_mut(b), player.get(a)) {
-                    Some((s, p))
-                } else {
-                    None
-                }
-            {
-                // debug!("The player has crashed into an alien ship");
-                alien_ship.decrease(25.0, Difficulty::Normal);
-            }
+pub fn laser_hit_ship(
+    time: Res<Time>,
+    mut hits: EventReader<LaserHitShip>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut ships: LaserTargets,
+    mut lasers: Query<(&Transform, &Velocity, &mut Laser)>,
+    mut effects: LaserHitEffects,
+    settings: Res<GameSettings>,
+) {
+    for &LaserHitShip { laser, ship } in hits.read() {
+        let (
+            Ok((
+                st,
+                sv,
+                &ColliderMassProperties::Mass(mass),
+                mut hp,
+                credit,
+                mothership,
+                is_player,
+            )),
+            Ok((lt, lv, mut laser_data)),
+        ) = (ships.get_mut(ship), lasers.get_mut(laser))
+        else {
+            continue;
+        };
+        // The player's lasers fly through the player, but aliens shoot each other.
+        if (is_player && laser_data.origin == LaserOrigin::Player) || laser_data.shooter == ship {
+            continue;
+        }
+        despawn_queue.1.insert(laser);
+        // Spent on a hardpoint earlier this tick.
+        if laser_data.damage <= 0.0 {
+            continue;
+        }
+        // A mothership's shields soak up lasers.
+        if mothership.is_some_and(|mothership| mothership.shields_up()) {
+            laser_data.damage = 0.0;
+            continue;
+        }
+        let difficulty = if is_player {
+            settings.difficulty
+        } else {
+            Difficulty::Normal
+        };
+        let hit = Hit {
+            cause: DamageCause::Laser,
+            by: Some(laser_data.shooter),
+        };
+        let amount = hp.decrease(laser_data.damage, difficulty, hit);
+        laser_data.damage = 0.0;
+        if is_player && amount > 0.0 {
+            effects.player_damaged.send(PlayerDamaged { amount, hit });
+        }
+        if let (Some(mut credit), LaserOrigin::Player) = (credit, &laser_data.origin) {
+            credit.player_hit_at = Some(time.elapsed_seconds());
+        }
 
-            // Check for player ship hitting celestial body
-            if let Some(((player_entity, _pt, _pv, mut player_hp), _)) =
-                if let (Ok(p), Ok(b)) = (player.get_mut(a), celestial_bodies.get(b)) {
-                    Some((p, b))
-                } else if let (Ok(p), Ok(b)) = (player.get_mut(b), celestial_bodies.get(a)) {
-                    Some((p, b))
-                } else {
-                    None
-                }
-            {
-                // debug!("The player has crashed into an alien ship");
-                let hp = player_hp.max;
-                player_hp.decrease(hp, settings.difficulty);
-
-                if settings.difficulty == Difficulty::GodMode {
-                    let (gravity, _thruster, _transform) =
-                        player_movement_query.get(player_entity).unwrap();
-
-                    impulses.send(AddExternalImpulse {
-                        entity: player_entity,
-                        impulse: (gravity.last_acceleration * -0.4)
-                            .rotate(Vec2::from_angle(PI / 4.)),
-                        torque_impulse: 0.,
-                    });
-                }
-            }
+        // Compute linear and angular knockback
+        let dv = (sv.linvel - lv.linvel).normalize_or_zero();
+        let dp = (st.translation.xy() - lt.translation.xy()).normalize_or_zero();
+        let linear = dp * LASER_HIT_LINEAR_IMPULSE * -dv.dot(dp) * mass;
+        let angular = LASER_HIT_ANGULAR_IMPULSE * -dv.perp_dot(dp) * mass;
+        effects.impulses.send(AddExternalImpulse {
+            entity: ship,
+            impulse: linear,
+            torque_impulse: angular,
+        })
+    }
+}
 
-            // Check for alien ship hitting celestial body
-            if let Some(((_e, mut alien_hp, _, _), _)) =
-                if let (Ok(p), Ok(b)) = (alien_ships.get_mut(a), celestial_bodies.get(b)) {
-                    Some((p, b))
-                } else if let (Ok(p), Ok(b)) = (alien_ships.get_mut(b), celestial_bodies.get(a)) {
-                    Some((p, b))
-                } else {
-                    None
-                }
-            {
-                // debug!("The player has crashed into an alien ship");
-                let hp = alien_hp.max;
-                alien_hp.decrease(hp, Difficulty::Normal);
-            }
+pub fn laser_hit_hardpoint(
+    time: Res<Time>,
+    mut hits: EventReader<LaserHitHardpoint>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+    mut hardpoints: Query<(&Parent, &Hardpoint, &mut HealthPoints)>,
+    mut motherships: Query<(&Mothership, &mut KillCredit)>,
+    mut lasers: Query<&mut Laser>,
+) {
+    for &LaserHitHardpoint { laser, hardpoint } in hits.read() {
+        let (Ok((parent, hardpoint, mut hp)), Ok(mut laser_data)) =
+            (hardpoints.get_mut(hardpoint), lasers.get_mut(laser))
+        else {
+            continue;
+        };
+        let Ok((mothership, mut credit)) = motherships.get_mut(parent.get()) else {
+            continue;
+        };
+        // Turrets fire through the rest of their mothership.
+        if laser_data.shooter == parent.get() {
+            continue;
+        }
+        despawn_queue.1.insert(laser);
+        // Spent on a neighbouring hardpoint.
+        if laser_data.damage <= 0.0 {
+            continue;
+        }
+        // Only shield generators are exposed while the shields are up.
+        if mothership.shields_up() && hardpoint.kind != HardpointKind::ShieldGenerator {
+            laser_data.damage = 0.0;
+            continue;
+        }
+        hp.decrease(
+            laser_data.damage,
+            Difficulty::Normal,
+            Hit {
+                cause: DamageCause::Laser,
+                by: Some(laser_data.shooter),
+            },
+        );
+        laser_data.damage = 0.0;
+        if laser_data.origin == LaserOrigin::Player {
+            credit.player_hit_at = Some(time.elapsed_seconds());
+        }
+    }
+}
+
+pub fn laser_hit_body(
+    mut hits: EventReader<LaserHitBody>,
+    mut despawn_queue: ResMut<DespawnQueue>,
+) {
+    for hit in hits.read() {
+        despawn_queue.1.insert(hit.laser);
+    }
+}
 
-            // Check for laser hitting celestial body
-            if let Some((_, (laser_entity, _, _, _))) =
-                if let (Ok(s), Ok(l)) = (celestial_bodies.get(a), lasers.get(b)) {
-                    Some((s, l))
-                } else if let (Ok(s), Ok(l)) = (celestial_bodies.get(b), lasers.get(a)) {
-                    Some((s, l))
-                } else {
-                    None
+// The player's touchdowns are handled first, so that a landing doesn't hurt.
+pub fn impact_damage_on_ships(
+    time: Res<Time>,
+    settings: Res<GameSettings>,
+    mut body_hits: EventReader<ShipHitBody>,
+    mut rams: EventReader<ShipRammedShip>,
+    mut ships: Ships,
+    mut player_damaged: EventWriter<PlayerDamaged>,
+    mut body_impacts: EventWriter<BodyImpact>,
+) {
+    // A ship can be hit several times in a frame, the impulses add up before turning into energy.
+    // The hardest of the hits gets the blame.
+    let mut impulses: HashMap<Entity, (f32, f32, Hit)> = HashMap::new();
+    let mut add = |ship: Entity, impulse: f32, hit: Hit| {
+        let (total, hardest, blamed) = impulses.entry(ship).or_insert((0.0, 0.0, hit));
+        *total += impulse;
+        if impulse > *hardest {
+            (*hardest, *blamed) = (impulse, hit);
+        }
+    };
+    for hit in body_hits.read() {
+        add(
+            hit.ship,
+            hit.impulse,
+            Hit {
+                cause: DamageCause::Crash,
+                by: Some(hit.body),
+            },
+        );
+        if let Ok((t, v, _, &ColliderMassProperties::Mass(mass), _, _)) = ships.get(hit.ship) {
+            if impact_damage(hit.impulse, mass) > 0.0 {
+                body_impacts.send(BodyImpact {
+                    ship: hit.ship,
+                    body: hit.body,
+                    position: t.translation.xy(),
+                    velocity: v.linvel,
+                    impulse: hit.impulse,
+                });
+            }
+        }
+    }
+    for ram in rams.read() {
+        let motion = |ship: Entity| {
+            let (t, v, ..) = ships.get(ship).ok()?;
+            Some((ship, t.translation.xy(), v.linvel))
+        };
+        let rammer = motion(ram.ships[0])
+            .zip(motion(ram.ships[1]))
+            .map(|(a, b)| rammer(a, b));
+        for (ship, other) in [(ram.ships[0], ram.ships[1]), (ram.ships[1], ram.ships[0])] {
+            let other_is_player = ships.get(other).is_ok_and(|(.., is_player)| is_player);
+            // A ship ramming the player, like a kamikaze, wrecks itself on its own.
+            let by = (!other_is_player || rammer == Some(other)).then_some(other);
+            if other_is_player && by.is_some() {
+                if let Ok((.., Some(mut credit), _)) = ships.get_mut(ship) {
+                    credit.player_hit_at = Some(time.elapsed_seconds());
                 }
-            {
-                // debug!("An alien ship has been hit");
-                despawn_queue.1.insert(laser_entity);
             }
-
-            // // Check for alien ship hitting alien ship
-            // if let (Ok(a1), Ok(a2)) = (alien_ships.get(a), alien_ships.get(b)) {
-            //     // debug!("Two alien ships crashed into each other");
-            //     despawn_queue.1.insert(a1);
-            //     despawn_queue.1.insert(a2);
-            // }
+            add(
+                ship,
+                ram.impulse,
+                Hit {
+                    cause: DamageCause::Ramming,
+                    by,
+                },
+            );
+        }
+    }
+    for (entity, (impulse, _, hit)) in impulses {
+        let Ok((_, _, mut hp, &ColliderMassProperties::Mass(mass), _, is_player)) =
+            ships.get_mut(entity)
+        else {
+            continue;
+        };
+        let difficulty = if is_player {
+            settings.difficulty
+        } else {
+            Difficulty::Normal
+        };
+        let amount = hp.decrease(impact_damage(impulse, mass), difficulty, hit);
+        if is_player && amount > 0.0 {
+            player_damaged.send(PlayerDamaged { amount, hit });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use bevy::math::Vec2;
+
+    use super::{classify, impact_damage, rammer, CollidingKind, CollisionPair};
+
+    #[test]
+    fn impact_damage_scales_with_energy() {
+        // Scrapes are harmless.
+        assert_eq!(impact_damage(10.0, 1.0), 0.0);
+        // Twice the impulse, four times the energy.
+        let damage = impact_damage(1000.0, 4.0);
+        assert!((impact_damage(2000.0, 4.0) / damage - 4.0).abs() < 1e-3);
+        // The same impulse hurts a light ship more.
+        assert!(impact_damage(1000.0, 1.0) > damage);
+    }
+
+    #[test]
+    fn the_ship_closing_in_faster_is_the_rammer() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        let (pa, pb) = (Vec2::ZERO, Vec2::new(60.0, 0.0));
+        // Running into a ship that flies across.
+        assert_eq!(
+            rammer(
+                (a, pa, Vec2::new(400.0, 0.0)),
+                (b, pb, Vec2::new(0.0, 300.0))
+            ),
+            a
+        );
+        // Head-on, the faster one rams the other.
+        assert_eq!(
+            rammer(
+                (a, pa, Vec2::new(100.0, 0.0)),
+                (b, pb, Vec2::new(-500.0, 0.0))
+            ),
+            b
+        );
+    }
+
+    #[test]
+    fn collision_pairs_are_classified_in_either_order() {
+        let (a, b) = (Entity::from_raw(1), Entity::from_raw(2));
+        assert_eq!(
+            classify((a, CollidingKind::Laser), (b, CollidingKind::Ship)),
+            Some(CollisionPair::LaserShip { laser: a, ship: b })
+        );
+        assert_eq!(
+            classify((b, CollidingKind::Ship), (a, CollidingKind::Laser)),
+            Some(CollisionPair::LaserShip { laser: a, ship: b })
+        );
+        assert_eq!(
+            classify((b, CollidingKind::Body), (a, CollidingKind::Ship)),
+            Some(CollisionPair::ShipBody { ship: a, body: b })
+        );
+        assert_eq!(
+            classify((b, CollidingKind::Hardpoint), (a, CollidingKind::Laser)),
+            Some(CollisionPair::LaserHardpoint {
+                laser: a,
+                hardpoint: b
+            })
+        );
+        assert_eq!(
+            classify((a, CollidingKind::Laser), (b, CollidingKind::Laser)),
+            None
+        );
+        assert_eq!(
+            classify((a, CollidingKind::Body), (b, CollidingKind::Body)),
+            None
+        );
+    }
+}
@@ -1,4 +1,9 @@
-use bevy::prelude::*;
+use std::sync::Arc;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
 use bevy_rapier2d::{
     dynamics::Velocity,
     geometry::{Collider, ColliderMassProperties},
@@ -7,17 +12,67 @@ use bevy_rapier2d::{
 use crate::{
     alien_ship::AlienShipMarker,
     celestial_body::{CelestialBodyMarker, CircularOrbitChain},
-    gravity::plan_course,
-    player::PlayerMarker,
+    gravity::{plan_course, CoursePlanning, PlannedShip},
+    landing::Landed,
+    maneuver::{plan_maneuvers, ManeuverNode, ManeuverPlan, PlannedManeuvers, PlannedNode},
+    player::{PlayerMarker, PLAYER_MASS},
+    simulation::SimulationMode,
+    thruster::Thruster,
 };
 
 pub const PLAYER_PLAN_DURATION: f32 = 30.0;
 pub const PLAYER_PLAN_STEP_DT: f32 = 0.05;
+const PLAYER_PLAN_TOLERANCE: f32 = 0.5;
+// While the player is thrusting, we also predict where they'd go if they kept burning for this long.
+pub const PLAYER_BURN_PREDICTION_S: f32 = 5.0;
 
-const MAX_ENEMY_TRAJECTORIES_COMPUTED_PER_FRAME: u32 = 60;
+const MAX_ENEMY_PLANNING_TASKS_IN_FLIGHT: usize = 256;
 const STALE_TRAJECTORY_AGE: f32 = 5.0;
-const ENEMY_PLAN_DURATION: f32 = 10.0;
-const ENEMY_PLAN_STEP_DT: f32 = 0.5;
+pub const ENEMY_PLAN_DURATION: f32 = 10.0;
+pub const ENEMY_PLAN_STEP_DT: f32 = 0.5;
+pub const ENEMY_PLAN_TOLERANCE: f32 = 5.0;
+
+const PLAYER_PLAN: PlanSettings = PlanSettings {
+    duration: PLAYER_PLAN_DURATION,
+    step_dt: PLAYER_PLAN_STEP_DT,
+    tolerance: PLAYER_PLAN_TOLERANCE,
+};
+const ENEMY_PLAN: PlanSettings = PlanSettings {
+    duration: ENEMY_PLAN_DURATION,
+    step_dt: ENEMY_PLAN_STEP_DT,
+    tolerance: ENEMY_PLAN_TOLERANCE,
+};
+
+type PlanningShip<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    &'a Collider,
+    &'a ComputedTrajectory,
+);
+
+pub type PlannedBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static ColliderMassProperties,
+        &'static Collider,
+        &'static CircularOrbitChain,
+    ),
+    With<CelestialBodyMarker>,
+>;
+
+// Ships with no planning task in flight.
+type NotPlanning<Marker> = (With<Marker>, Without<PlanningTask>);
+type InFlight<Marker> = (NotPlanning<Marker>, Without<Landed>);
+
+struct PlanSettings {
+    duration: f32,
+    step_dt: f32,
+    tolerance: f32,
+}
 
 #[derive(Component)]
 pub struct ComputedTrajectory {
@@ -26,6 +81,159 @@ pub struct ComputedTrajectory {
     pub step_dt: f32,
     pub path: Vec<(Vec2, f32)>, // (path, distance to nearest object)
     pub closest_flyby: f32,     // will be 0.0 if the trajectory is a collision course
+    pub impact: Option<PredictedImpact>,
+    pub burn: Option<PlannedBurn>, // the path if we keep thrusting, for the player
+    pub maneuvers: Option<PlannedManeuverCourse>, // the path through the player's maneuver nodes
+}
+
+pub struct PlannedManeuverCourse {
+    pub nodes: Vec<PlannedNode>,
+    pub path: Vec<(Vec2, f32)>, // from the first node on
+    pub impact: Option<PredictedImpact>,
+}
+
+pub struct PlannedBurn {
+    pub duration: f32,
+    pub path: Vec<(Vec2, f32)>,
+    pub impact: Option<PredictedImpact>,
+}
+
+#[derive(Clone, Copy)]
+pub struct PredictedImpact {
+    pub at: f32, // elapsed game time
+    pub body: Entity,
+}
+
+impl ComputedTrajectory {
+    pub fn time_to_impact(&self, now: f32) -> Option<f32> {
+        self.impact.map(|impact| impact.at - now)
+    }
+
+    // Moves every planned position by `delta`, when the world is rebased.
+    pub fn shift(&mut self, delta: Vec2) {
+        let shift_path = |path: &mut Vec<(Vec2, f32)>| {
+            for (pos, _) in path.iter_mut() {
+                *pos += delta;
+            }
+        };
+        shift_path(&mut self.path);
+        if let Some(burn) = self.burn.as_mut() {
+            shift_path(&mut burn.path);
+        }
+        if let Some(maneuvers) = self.maneuvers.as_mut() {
+            shift_path(&mut maneuvers.path);
+            for node in maneuvers.nodes.iter_mut() {
+                node.pos += delta;
+            }
+        }
+    }
+
+    // The planned distance to the nearest body's surface at `t`, as long as the plan covers it.
+    pub fn altitude_at(&self, t: f32) -> Option<f32> {
+        if self.step_dt <= 0.0 || t < self.computed_at {
+            return None;
+        }
+        let i = ((t - self.computed_at) / self.step_dt) as usize;
+        self.path.get(i).map(|&(_, altitude)| altitude)
+    }
+
+    // The planned position at `t`, as long as the plan covers it.
+    pub fn position_at(&self, t: f32) -> Option<Vec2> {
+        if self.step_dt <= 0.0 || t < self.computed_at {
+            return None;
+        }
+        let i = ((t - self.computed_at) / self.step_dt) as usize;
+        self.path.get(i).map(|&(pos, _)| pos)
+    }
+
+    fn store(&mut self, now: f32, step_dt: f32, planned: PlannedCourses, bodies: &[Entity]) {
+        let predicted_impact = |course: &CoursePlanning| {
+            course.impact.as_ref().map(|impact| PredictedImpact {
+                at: now + impact.time,
+                body: bodies[impact.body],
+            })
+        };
+        self.computed_at = now;
+        self.step_dt = step_dt;
+        self.impact = predicted_impact(&planned.coasting);
+        self.path = planned.coasting.path;
+        self.closest_flyby = planned.coasting.closest_flyby;
+        self.burn = planned.burning.map(|(duration, course)| PlannedBurn {
+            duration,
+            impact: predicted_impact(&course),
+            path: course.path,
+        });
+        self.maneuvers = planned.maneuvers.map(|maneuvers| PlannedManeuverCourse {
+            impact: predicted_impact(&maneuvers.course),
+            nodes: maneuvers.nodes,
+            path: maneuvers.course.path,
+        });
+    }
+}
+
+struct PlannedCourses {
+    coasting: CoursePlanning,
+    burning: Option<(f32, CoursePlanning)>, // (burn duration, course)
+    maneuvers: Option<PlannedManeuvers>,
+}
+
+// A trajectory being planned on the async compute pool.
+// The plan starts from where the ship was when the task was dispatched.
+#[derive(Component)]
+pub struct PlanningTask {
+    dispatched_at: f32,
+    step_dt: f32,
+    shift: Vec2, // how much the world was rebased while planning
+    bodies: Arc<Vec<Entity>>,
+    task: Task<PlannedCourses>,
+}
+
+// The celestial bodies as they were when planning tasks were dispatched.
+// Orbits are deterministic, so a snapshot is enough for the tasks to predict their motion.
+pub struct BodiesSnapshot {
+    pub entities: Arc<Vec<Entity>>,
+    pub bodies: Arc<Vec<(f32, f32, CircularOrbitChain)>>, // (mass, radius, orbit)
+}
+
+impl PlanningTask {
+    pub fn shift(&mut self, delta: Vec2) {
+        self.shift += delta;
+    }
+}
+
+impl BodiesSnapshot {
+    fn dispatch(
+        &self,
+        now: f32,
+        settings: &PlanSettings,
+        coasting: PlannedShip,
+        burning: Option<PlannedShip>,
+        nodes: Vec<ManeuverNode>,
+    ) -> PlanningTask {
+        let bodies = self.bodies.clone();
+        let (max_dt, step_dt, tolerance) =
+            (settings.duration, settings.step_dt, settings.tolerance);
+        PlanningTask {
+            dispatched_at: now,
+            step_dt,
+            shift: Vec2::ZERO,
+            bodies: self.entities.clone(),
+            task: AsyncComputeTaskPool::get().spawn(async move {
+                let plan =
+                    |ship: &PlannedShip| plan_course(max_dt, step_dt, tolerance, ship, &bodies);
+                PlannedCourses {
+                    maneuvers: (!nodes.is_empty()).then(|| {
+                        plan_maneuvers(now, step_dt, tolerance, coasting.clone(), &nodes, &bodies)
+                    }),
+                    coasting: plan(&coasting),
+                    burning: burning.map(|ship| {
+                        let duration = ship.thrust.map_or(0.0, |thrust| thrust.burn_duration);
+                        (duration, plan(&ship))
+                    }),
+                }
+            }),
+        }
+    }
 }
 
 impl Default for ComputedTrajectory {
@@ -36,104 +244,129 @@ impl Default for ComputedTrajectory {
             step_dt: 0.0,
             path: vec![],
             closest_flyby: f32::INFINITY,
+            impact: None,
+            burn: None,
+            maneuvers: None,
         }
     }
 }
 
 pub fn compute_player_trajectory(
+    mut commands: Commands,
     time: Res<Time>,
-    mut player: Query<(&Transform, &Velocity, &mut ComputedTrajectory), With<PlayerMarker>>,
-    bodies: Query<
-        (
-            &Transform,
-            &ColliderMassProperties,
-            &Collider,
-            &CircularOrbitChain,
-        ),
-        With<CelestialBodyMarker>,
-    >,
+    maneuver_plan: Res<ManeuverPlan>,
+    player: Query<(PlanningShip, &Thruster), InFlight<PlayerMarker>>,
+    bodies: PlannedBodies,
 ) {
-    if let Ok((t, v, mut traj)) = player.get_single_mut() {
+    if let Ok(((entity, t, v, collider, traj), thruster)) = player.get_single() {
         if traj.computation_requested {
-            let planned_course = plan_course(
-                PLAYER_PLAN_DURATION,
-                PLAYER_PLAN_STEP_DT,
-                t.translation.xy(),
-                v.linvel,
-                &collect_celestial_bodies(bodies),
+            let ship = |burn_duration: f32| PlannedShip {
+                start_time: 0.0,
+                pos: t.translation.xy(),
+                velocity: v.linvel,
+                radius: scaled_radius(collider, t),
+                thrust: Some(thruster.profile(t.up().xy(), PLAYER_MASS, burn_duration)),
+            };
+            let task = collect_celestial_bodies(&bodies).dispatch(
+                time.elapsed_seconds(),
+                &PLAYER_PLAN,
+                ship(0.0),
+                (thruster.current_thrust > 0.0).then(|| ship(PLAYER_BURN_PREDICTION_S)),
+                maneuver_plan.upcoming(time.elapsed_seconds()),
             );
-            traj.computed_at = time.elapsed_seconds();
-            traj.step_dt = PLAYER_PLAN_STEP_DT;
-            traj.path = planned_course.path;
-            traj.closest_flyby = planned_course.closest_flyby;
+            commands.entity(entity).insert(task);
         }
     }
 }
 
 pub fn compute_enemies_trajectories(
+    mut commands: Commands,
     time: Res<Time>,
-    mut ships: Query<
-        (Entity, &Transform, &Velocity, &mut ComputedTrajectory),
-        With<AlienShipMarker>,
-    >,
-    bodies: Query<
-        (
-            &Transform,
-            &ColliderMassProperties,
-            &Collider,
-            &CircularOrbitChain,
-        ),
-        With<CelestialBodyMarker>,
-    >,
+    ships: Query<PlanningShip, NotPlanning<AlienShipMarker>>,
+    tasks: Query<(), (With<AlienShipMarker>, With<PlanningTask>)>,
+    bodies: PlannedBodies,
 ) {
-    let bodies = collect_celestial_bodies(bodies);
+    let bodies = collect_celestial_bodies(&bodies);
 
-    let mut total_computed = 0u32;
-    for (_, t, v, mut traj) in ships.iter_mut() {
-        if total_computed >= MAX_ENEMY_TRAJECTORIES_COMPUTED_PER_FRAME {
+    let mut in_flight = tasks.iter().count();
+    for (entity, t, v, collider, traj) in ships.iter() {
+        if in_flight >= MAX_ENEMY_PLANNING_TASKS_IN_FLIGHT {
             break;
         }
         if traj.computation_requested
             && time.elapsed_seconds() - traj.computed_at >= STALE_TRAJECTORY_AGE
         {
-            let planned_course = plan_course(
-                ENEMY_PLAN_DURATION,
-                ENEMY_PLAN_STEP_DT,
-                t.translation.xy(),
-                v.linvel,
-                &bodies,
+            let task = bodies.dispatch(
+                time.elapsed_seconds(),
+                &ENEMY_PLAN,
+                PlannedShip {
+                    start_time: 0.0,
+                    pos: t.translation.xy(),
+                    velocity: v.linvel,
+                    radius: scaled_radius(collider, t),
+                    thrust: None,
+                },
+                None,
+                vec![],
+            );
+            commands.entity(entity).insert(task);
+            in_flight += 1;
+        }
+    }
+}
+
+pub fn apply_planned_trajectories(
+    mut commands: Commands,
+    mode: Res<SimulationMode>,
+    mut ships: Query<(Entity, &mut ComputedTrajectory, &mut PlanningTask)>,
+) {
+    for (entity, mut traj, mut planning) in ships.iter_mut() {
+        // In deterministic mode, plans must land on the tick they were requested on.
+        let wait = matches!(*mode, SimulationMode::Deterministic { .. });
+        if wait || planning.task.is_finished() {
+            let planned = block_on(&mut planning.task);
+            traj.store(
+                planning.dispatched_at,
+                planning.step_dt,
+                planned,
+                &planning.bodies,
             );
-            traj.computed_at = time.elapsed_seconds();
-            traj.step_dt = ENEMY_PLAN_STEP_DT;
-            traj.path = planned_course.path;
-            traj.closest_flyby = planned_course.closest_flyby;
-            total_computed += 1;
+            traj.shift(planning.shift);
+            commands.entity(entity).remove::<PlanningTask>();
         }
     }
 }
 
-fn collect_celestial_bodies(
-    q: Query<
-        (
-            &Transform,
-            &ColliderMassProperties,
-            &Collider,
-            &CircularOrbitChain,
-        ),
-        With<CelestialBodyMarker>,
-    >,
-) -> Vec<(f32, f32, CircularOrbitChain)> {
-    q.iter()
-        .map(|(_, massprops, collider, circular_orbit)| {
+// Rapier scales colliders with their transform.
+// Ships and bodies are balls, anything else is approximated by its bounding circle.
+pub fn scaled_radius(collider: &Collider, transform: &Transform) -> f32 {
+    let radius = match collider.as_ball() {
+        Some(ball) => ball.radius(),
+        None => collider.raw.compute_local_bounding_sphere().radius(),
+    };
+    radius * transform.scale.x
+}
+
+pub fn collect_celestial_bodies(q: &PlannedBodies) -> BodiesSnapshot {
+    let (entities, bodies) = q
+        .iter()
+        .map(|(entity, transform, massprops, collider, circular_orbit)| {
             if let ColliderMassProperties::Mass(mass) = massprops {
                 (
-                    *mass,
-                    collider.as_ball().unwrap().radius(),
-                    circular_orbit.clone(),
+                    entity,
+                    (
+                        *mass,
+                        scaled_radius(collider, transform),
+                        circular_orbit.clone(),
+                    ),
                 )
             } else {
                 panic!("Use ColliderMassProperties::Mass(x) for celestial bodies")
             }
         })
-        .collect::<Vec<(f32, f32, CircularOrbitChain)>>()
+        .unzip();
+    BodiesSnapshot {
+        entities: Arc::new(entities),
+        bodies: Arc::new(bodies),
+    }
 }
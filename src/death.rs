@@ -1,26 +1,171 @@
 use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
 
 use crate::{
-    alien_ship::AlienShipMarker, despawn_queue::DespawnQueue, healthpoints::HealthPoints,
-    player::PlayerMarker, ui::Score, AppState,
+    alien_ship::AlienShipMarker,
+    despawn_queue::DespawnQueue,
+    gameplay_events::ShipDestroyed,
+    healthpoints::{DamageCause, HealthPoints},
+    player::PlayerMarker,
+    AppState,
 };
 
+// An alien crashing this long after the player last hurt it still counts as the player's kill.
+const KILL_CREDIT_WINDOW_S: f32 = 5.0;
+// An alien crashing this close to the player, soon after chasing it, was lured into it.
+const LURE_RANGE: f32 = 2500.0;
+
+// When the player last shot or rammed this ship, and when the ship last went after the player.
+#[derive(Component, Default)]
+pub struct KillCredit {
+    pub player_hit_at: Option<f32>,
+    pub chased_player_at: Option<f32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum KillKind {
+    Shot,
+    Rammed,
+    Knocked, // into a body, after the player hit it
+    Lured,   // into a body, while chasing the player
+    Unassisted,
+}
+
+impl KillKind {
+    pub const ALL: [KillKind; 5] = [
+        KillKind::Shot,
+        KillKind::Rammed,
+        KillKind::Knocked,
+        KillKind::Lured,
+        KillKind::Unassisted,
+    ];
+
+    pub fn credited(&self) -> bool {
+        *self != KillKind::Unassisted
+    }
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            KillKind::Shot => "Shot down",
+            KillKind::Rammed => "Rammed",
+            KillKind::Knocked => "Knocked into a planet",
+            KillKind::Lured => "Lured into a crash",
+            KillKind::Unassisted => "Lost on their own",
+        }
+    }
+}
+
+pub fn attribute(
+    cause: Option<DamageCause>,
+    killed_by_player: bool,
+    since_player_hit: Option<f32>,
+    since_chased_player: Option<f32>,
+    player_distance: f32,
+) -> KillKind {
+    let within_window = |since: Option<f32>| since.is_some_and(|dt| dt <= KILL_CREDIT_WINDOW_S);
+    match cause {
+        Some(DamageCause::Laser) if killed_by_player => KillKind::Shot,
+        Some(DamageCause::Ramming) if killed_by_player => KillKind::Rammed,
+        Some(DamageCause::Crash) if within_window(since_player_hit) => KillKind::Knocked,
+        Some(DamageCause::Crash)
+            if within_window(since_chased_player) && player_distance <= LURE_RANGE =>
+        {
+            KillKind::Lured
+        }
+        _ => KillKind::Unassisted,
+    }
+}
+
 pub fn update(
+    time: Res<Time>,
     mut despawn_queue: ResMut<DespawnQueue>,
     mut next_state: ResMut<NextState<AppState>>,
-    player_hp: Query<&HealthPoints, With<PlayerMarker>>,
-    alien_ships: Query<(Entity, &HealthPoints), With<AlienShipMarker>>,
-    mut score: ResMut<Score>,
+    player_hp: Query<(Entity, &Transform, &Velocity, &HealthPoints), With<PlayerMarker>>,
+    alien_ships: Query<
+        (Entity, &Transform, &Velocity, &HealthPoints, &KillCredit),
+        With<AlienShipMarker>,
+    >,
+    mut destroyed: EventWriter<ShipDestroyed>,
 ) {
-    if let Ok(&HealthPoints { current, .. }) = player_hp.get_single() {
-        if current <= 0.0 {
-            next_state.set(AppState::DeathScreen);
-        }
+    let destruction =
+        |ship: Entity, t: &Transform, v: &Velocity, hp: &HealthPoints| ShipDestroyed {
+            ship,
+            position: t.translation.xy(),
+            velocity: v.linvel,
+            is_player: false,
+            cause: hp.last_hit.map(|hit| hit.cause),
+            killer: hp.last_hit.and_then(|hit| hit.by),
+            kill: None,
+        };
+    let Ok((player, pt, pv, player_hp)) = player_hp.get_single() else {
+        return;
+    };
+    // The state only changes on the next frame, don't die twice meanwhile.
+    if player_hp.current <= 0.0 && next_state.0 != Some(AppState::DeathScreen) {
+        next_state.set(AppState::DeathScreen);
+        destroyed.send(ShipDestroyed {
+            is_player: true,
+            ..destruction(player, pt, pv, player_hp)
+        });
     }
-    for (entity, &HealthPoints { current, .. }) in alien_ships.iter() {
-        if current <= 0.0 {
-            despawn_queue.1.insert(entity);
-            score.enemies_killed += 1;
+    for (entity, t, v, hp, credit) in alien_ships.iter() {
+        if hp.current <= 0.0 && despawn_queue.1.insert(entity) {
+            let event = destruction(entity, t, v, hp);
+            let kill = attribute(
+                event.cause,
+                event.killer == Some(player),
+                credit.player_hit_at.map(|at| time.elapsed_seconds() - at),
+                credit
+                    .chased_player_at
+                    .map(|at| time.elapsed_seconds() - at),
+                t.translation.distance(pt.translation),
+            );
+            destroyed.send(ShipDestroyed {
+                kill: Some(kill),
+                ..event
+            });
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{attribute, KillKind};
+    use crate::healthpoints::DamageCause;
+
+    #[test]
+    fn indirect_kills_are_credited_within_the_window() {
+        let far = 1e6;
+        assert_eq!(
+            attribute(Some(DamageCause::Laser), true, Some(0.0), None, far),
+            KillKind::Shot
+        );
+        // Friendly fire
+        assert_eq!(
+            attribute(Some(DamageCause::Laser), false, Some(0.0), None, far),
+            KillKind::Unassisted
+        );
+        assert_eq!(
+            attribute(Some(DamageCause::Crash), false, Some(2.0), None, far),
+            KillKind::Knocked
+        );
+        assert_eq!(
+            attribute(Some(DamageCause::Crash), false, Some(60.0), None, far),
+            KillKind::Unassisted
+        );
+        assert_eq!(
+            attribute(Some(DamageCause::Crash), false, None, Some(1.0), 100.0),
+            KillKind::Lured
+        );
+        // Crashing near the player on its own errands.
+        assert_eq!(
+            attribute(Some(DamageCause::Crash), false, None, None, 100.0),
+            KillKind::Unassisted
+        );
+        assert_eq!(
+            attribute(Some(DamageCause::Crash), false, None, Some(60.0), 100.0),
+            KillKind::Unassisted
+        );
+        assert!(!KillKind::Unassisted.credited());
+    }
+}
@@ -1,6 +1,6 @@
 use bevy::{prelude::*, utils::HashSet};
 
-use crate::system_sets::AppStage;
+use crate::{simulation::SimulationMode, system_sets::AppStage};
 
 #[derive(Resource, Default, Clone)]
 pub struct DespawnQueue(pub HashSet<Entity>, pub HashSet<Entity>);
@@ -16,6 +16,7 @@ pub fn despawn_entities(mut commands: Commands, mut queue: ResMut<DespawnQueue>)
 }
 
 pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
     app.insert_resource(DespawnQueue::default());
-    app.add_systems(Update, despawn_entities.in_set(AppStage::DespawnQueue));
+    app.add_systems(schedule, despawn_entities.in_set(AppStage::DespawnQueue));
 }
@@ -0,0 +1,154 @@
+use bevy::prelude::*;
+
+use crate::{
+    camera::UICameraMarker,
+    celestial_body::{CircularOrbitChain, OrbitHierarchy},
+    course_planner::{ComputedTrajectory, PlanningTask},
+    player::PlayerMarker,
+    simulation::SimulationMode,
+    system_sets::AppStage,
+    AppState,
+};
+
+// Past this distance from the origin, f32 positions get coarse enough to make rendering and physics jitter.
+const REBASE_DISTANCE: f32 = 16384.0;
+
+// The world is periodically recentered around the player.
+// Everything positioned in world space is shifted together, so the rebase is invisible to gameplay.
+#[derive(Resource, Default)]
+pub struct FloatingOrigin {
+    // Where the world's origin currently sits in the star system's coordinates.
+    pub offset: Vec2,
+}
+
+type WorldTransforms<'w, 's> = Query<
+    'w,
+    's,
+    (&'static mut Transform, Has<PlayerMarker>),
+    (Without<Parent>, Without<Node>, Without<UICameraMarker>),
+>;
+
+pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
+    app.init_resource::<FloatingOrigin>();
+    app.add_systems(OnEnter(AppState::Game), reset_floating_origin);
+    app.add_systems(
+        schedule,
+        rebase.before(AppStage::AI).run_if(in_state(AppState::Game)),
+    );
+}
+
+fn reset_floating_origin(mut commands: Commands) {
+    commands.insert_resource(FloatingOrigin::default());
+}
+
+pub fn rebase(
+    mut origin: ResMut<FloatingOrigin>,
+    mut transforms: WorldTransforms,
+    mut orbits: Query<&mut CircularOrbitChain>,
+    hierarchy: Option<ResMut<OrbitHierarchy>>,
+    mut trajectories: Query<&mut ComputedTrajectory>,
+    mut planning_tasks: Query<&mut PlanningTask>,
+) {
+    let Some(player_pos) = transforms
+        .iter()
+        .find_map(|(t, is_player)| is_player.then_some(t.translation.xy()))
+    else {
+        return;
+    };
+    if player_pos.length() < REBASE_DISTANCE {
+        return;
+    }
+    let delta = -player_pos.round();
+    origin.offset -= delta;
+    for (mut t, _) in transforms.iter_mut() {
+        t.translation += delta.extend(0.0);
+    }
+    for mut orbit in orbits.iter_mut() {
+        orbit.origin += delta;
+    }
+    if let Some(mut hierarchy) = hierarchy {
+        hierarchy.shift(delta);
+    }
+    for mut traj in trajectories.iter_mut() {
+        traj.shift(delta);
+    }
+    for mut task in planning_tasks.iter_mut() {
+        task.shift(delta);
+    }
+    debug!("Rebased the world origin to {:?}", origin.offset);
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::system::RunSystemOnce, prelude::*};
+
+    use super::{rebase, FloatingOrigin};
+    use crate::{
+        celestial_body::{CircularOrbitChain, CircularOrbitDef, OrbitDef},
+        course_planner::ComputedTrajectory,
+        player::PlayerMarker,
+    };
+
+    fn world_with_player_at(pos: Vec2) -> (World, Entity, Entity) {
+        let mut world = World::new();
+        world.init_resource::<FloatingOrigin>();
+        let player = world
+            .spawn((
+                PlayerMarker,
+                Transform::from_translation(pos.extend(0.0)),
+                ComputedTrajectory {
+                    path: vec![(pos + Vec2::X, 0.0)],
+                    ..default()
+                },
+            ))
+            .id();
+        let body = world
+            .spawn((
+                Transform::from_xyz(100.0, 0.0, 0.0),
+                CircularOrbitChain {
+                    origin: Vec2::ZERO,
+                    chain: vec![OrbitDef::Circular(CircularOrbitDef {
+                        theta: 0.0,
+                        radius: 100.0,
+                        freq: 0.1,
+                    })],
+                },
+            ))
+            .id();
+        (world, player, body)
+    }
+
+    #[test]
+    fn rebase_recenters_on_the_player() {
+        let far = Vec2::new(30000.0, -20000.0);
+        let (mut world, player, body) = world_with_player_at(far);
+        world.run_system_once(rebase);
+
+        assert_eq!(world.resource::<FloatingOrigin>().offset, far);
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::ZERO
+        );
+        assert_eq!(
+            world.get::<ComputedTrajectory>(player).unwrap().path[0].0,
+            Vec2::X
+        );
+        let orbit = world.get::<CircularOrbitChain>(body).unwrap();
+        let transform = world.get::<Transform>(body).unwrap();
+        assert_eq!(orbit.pos(0.0), transform.translation.xy());
+        assert_eq!(orbit.pos(0.0), Vec2::new(100.0, 0.0) - far);
+    }
+
+    #[test]
+    fn no_rebase_near_the_origin() {
+        let (mut world, player, _) = world_with_player_at(Vec2::new(1000.0, 0.0));
+        world.run_system_once(rebase);
+
+        assert_eq!(world.resource::<FloatingOrigin>().offset, Vec2::ZERO);
+        assert_eq!(
+            world.get::<Transform>(player).unwrap().translation,
+            Vec3::new(1000.0, 0.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,206 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{dynamics::Velocity, geometry::Collider};
+
+use crate::{
+    alien_ship::AlienShipMarker,
+    course_planner::scaled_radius,
+    death::KillKind,
+    healthpoints::{DamageCause, Hit},
+    landing::Landed,
+    mothership::HardpointKind,
+    player::PlayerMarker,
+    simulation::SimulationMode,
+    system_sets::AppStage,
+    AppState,
+};
+
+// Passing closer than this to an enemy's hull, fast enough, is a near miss.
+pub const NEAR_MISS_MAX_GAP: f32 = 100.0;
+const NEAR_MISS_MIN_SPEED: f32 = 300.0;
+
+type FlyingPlayer = (With<PlayerMarker>, Without<Landed>);
+
+// What the simulation tells scoring, HUD and effects about, so that they don't have to watch the world themselves.
+
+#[derive(Event)]
+pub struct ShipDestroyed {
+    pub ship: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub is_player: bool,
+    pub cause: Option<DamageCause>,
+    pub killer: Option<Entity>,
+    pub kill: Option<KillKind>, // how the player gets credit for an alien's death
+}
+
+#[derive(Event)]
+pub struct PlayerDamaged {
+    pub amount: f32, // after the difficulty multiplier
+    pub hit: Hit,
+}
+
+#[derive(Event)]
+pub struct WaveStarted {
+    pub wave: u32,
+    pub enemies: u32,
+    pub center: Vec2,
+}
+
+// The player flew past an enemy ship without touching it.
+#[derive(Event)]
+pub struct NearMiss {
+    pub ship: Entity,
+    pub gap: f32, // between the hulls
+    pub relative_speed: f32,
+}
+
+// A ship crashed into a celestial body hard enough to get hurt.
+#[derive(Event)]
+pub struct BodyImpact {
+    pub ship: Entity,
+    pub body: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+    pub impulse: f32,
+}
+
+// A mothership lost one of its hardpoints.
+#[derive(Event)]
+pub struct HardpointDestroyed {
+    pub mothership: Entity,
+    pub kind: HardpointKind,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
+    app.add_event::<ShipDestroyed>();
+    app.add_event::<PlayerDamaged>();
+    app.add_event::<WaveStarted>();
+    app.add_event::<NearMiss>();
+    app.add_event::<BodyImpact>();
+    app.add_event::<HardpointDestroyed>();
+    app.add_systems(
+        schedule,
+        detect_near_misses
+            .in_set(AppStage::Simulation)
+            .run_if(in_state(AppState::Game)),
+    );
+    app.add_systems(Update, log_gameplay_events.run_if(in_state(AppState::Game)));
+}
+
+// How close `relative_position` gets to zero if the closest approach happens within the next `dt`.
+pub fn closest_approach(relative_position: Vec2, relative_velocity: Vec2, dt: f32) -> Option<f32> {
+    let speed_squared = relative_velocity.length_squared();
+    if speed_squared == 0.0 {
+        return None;
+    }
+    let t = -relative_position.dot(relative_velocity) / speed_squared;
+    (0.0..dt)
+        .contains(&t)
+        .then(|| (relative_position + relative_velocity * t).length())
+}
+
+pub fn detect_near_misses(
+    time: Res<Time>,
+    player: Query<(&Transform, &Velocity, &Collider), FlyingPlayer>,
+    alien_ships: Query<(Entity, &Transform, &Velocity, &Collider), With<AlienShipMarker>>,
+    mut near_misses: EventWriter<NearMiss>,
+) {
+    let Ok((pt, pv, player_collider)) = player.get_single() else {
+        return;
+    };
+    let player_radius = scaled_radius(player_collider, pt);
+    for (ship, t, v, collider) in alien_ships.iter() {
+        let relative_velocity = v.linvel - pv.linvel;
+        let relative_speed = relative_velocity.length();
+        if relative_speed < NEAR_MISS_MIN_SPEED {
+            continue;
+        }
+        let relative_position = t.translation.xy() - pt.translation.xy();
+        let Some(distance) =
+            closest_approach(relative_position, relative_velocity, time.delta_seconds())
+        else {
+            continue;
+        };
+        let gap = distance - player_radius - scaled_radius(collider, t);
+        if gap > 0.0 && gap < NEAR_MISS_MAX_GAP {
+            near_misses.send(NearMiss {
+                ship,
+                gap,
+                relative_speed,
+            });
+        }
+    }
+}
+
+fn log_gameplay_events(
+    mut destroyed: EventReader<ShipDestroyed>,
+    mut damaged: EventReader<PlayerDamaged>,
+    mut waves: EventReader<WaveStarted>,
+    mut near_misses: EventReader<NearMiss>,
+    mut impacts: EventReader<BodyImpact>,
+    mut hardpoints: EventReader<HardpointDestroyed>,
+) {
+    for event in destroyed.read() {
+        debug!(
+            "Ship {:?} destroyed at {} (player: {}) by {:?} from {:?}, {:?}",
+            event.ship, event.position, event.is_player, event.cause, event.killer, event.kill
+        );
+    }
+    for event in damaged.read() {
+        debug!(
+            "Player lost {:.1} HP to {:?} from {:?}",
+            event.amount, event.hit.cause, event.hit.by
+        );
+    }
+    for event in waves.read() {
+        debug!(
+            "Wave {} started with {} ships around {}",
+            event.wave, event.enemies, event.center
+        );
+    }
+    for event in near_misses.read() {
+        debug!(
+            "Near miss with {:?}, {:.0} apart at {:.0}",
+            event.ship, event.gap, event.relative_speed
+        );
+    }
+    for event in impacts.read() {
+        debug!(
+            "Ship {:?} crashed into {:?} at {} with an impulse of {:.0}",
+            event.ship, event.body, event.position, event.impulse
+        );
+    }
+    for event in hardpoints.read() {
+        debug!(
+            "Mothership {:?} lost a {:?} at {}",
+            event.mothership, event.kind, event.position
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::closest_approach;
+
+    #[test]
+    fn closest_approach_within_the_frame() {
+        // Passing 50 below, crossing x = 0 a quarter through the frame.
+        let d = closest_approach(Vec2::new(-25.0, -50.0), Vec2::new(1000.0, 0.0), 0.1);
+        assert!((d.unwrap() - 50.0).abs() < 1e-3);
+        // Already moving apart.
+        assert_eq!(
+            closest_approach(Vec2::new(25.0, -50.0), Vec2::new(1000.0, 0.0), 0.1),
+            None
+        );
+        // Not there yet.
+        assert_eq!(
+            closest_approach(Vec2::new(-500.0, -50.0), Vec2::new(1000.0, 0.0), 0.1),
+            None
+        );
+    }
+}
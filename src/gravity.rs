@@ -1,10 +1,22 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_rapier2d::{dynamics::Velocity, geometry::ColliderMassProperties};
 
-use crate::celestial_body::CircularOrbitChain;
+use crate::{celestial_body::CircularOrbitChain, thruster::ThrustProfile};
 
 const GRAVITATIONAL_CONSTANT: f32 = 32.0;
 
+// Ships and lasers move in packs, so we evaluate the field once per occupied grid cell
+// and linearize it around the cell's center for every entity inside.
+const GRAVITY_GRID_CELL_SIZE: f32 = 1024.0;
+// The linearization is only used when every body is this far from the cell's center,
+// which keeps each body's contribution within ~1%. Cells closer to a body use the exact sum.
+const GRAVITY_GRID_MIN_BODY_DISTANCE: f32 = 16.0 * GRAVITY_GRID_CELL_SIZE;
+
+// Course planning substeps bounds.
+const PLAN_MIN_SUBSTEP_DT: f32 = 0.002;
+const PLAN_MAX_SUBSTEPS_PER_STEP: u32 = 64;
+const PLAN_PROXIMITY_FRACTION: f32 = 0.25;
+
 #[derive(Component)]
 pub struct AttractingBody;
 
@@ -13,14 +25,102 @@ pub struct AffectedByGravity {
     pub last_acceleration: Vec2,
 }
 
-fn gravity_formula(d: f32, m: f32) -> f32 {
+pub fn setup(app: &mut App) {
+    app.init_resource::<GravityField>();
+}
+
+pub fn gravity_formula(d: f32, m: f32) -> f32 {
     // Real spacetime is very scary, empty, and difficult to navigate.
     // This one is a little bit more intuitive.
     GRAVITATIONAL_CONSTANT * m / d.max(1.0).powf(1.6)
 }
 
+// The potential the d^1.6 law derives from. It vanishes at infinity, so ships can escape.
+pub fn gravity_potential(d: f32, m: f32) -> f32 {
+    -GRAVITATIONAL_CONSTANT * m / (0.6 * d.max(1.0).powf(0.6))
+}
+
+fn pairwise_acceleration(pos: Vec2, bodies: &[(Vec2, f32)]) -> Vec2 {
+    bodies
+        .iter()
+        .fold(Vec2::ZERO, |acceleration, &(opos, omass)| {
+            let d = opos - pos;
+            acceleration + d.normalize_or_zero() * gravity_formula(d.length(), omass)
+        })
+}
+
+// The attracting bodies' gravity field, rebuilt every frame by `update`.
+#[derive(Resource, Default)]
+pub struct GravityField {
+    bodies: Vec<(Vec2, f32)>, // (pos, mass)
+    cells: HashMap<IVec2, Option<CellExpansion>>,
+}
+
+// First order expansion of the field around a cell's center.
+#[derive(Clone, Copy)]
+struct CellExpansion {
+    center: Vec2,
+    acceleration: Vec2,
+    jacobian: Mat2,
+}
+
+impl GravityField {
+    pub fn new(bodies: Vec<(Vec2, f32)>) -> Self {
+        Self {
+            bodies,
+            cells: HashMap::default(),
+        }
+    }
+
+    pub fn cache_cells(&mut self, positions: impl Iterator<Item = Vec2>) {
+        for pos in positions {
+            let cell = GravityField::cell(pos);
+            if !self.cells.contains_key(&cell) {
+                let expansion = self.expand((cell.as_vec2() + 0.5) * GRAVITY_GRID_CELL_SIZE);
+                self.cells.insert(cell, expansion);
+            }
+        }
+    }
+
+    pub fn acceleration(&self, pos: Vec2) -> Vec2 {
+        match self.cells.get(&GravityField::cell(pos)) {
+            Some(Some(expansion)) => {
+                expansion.acceleration + expansion.jacobian * (pos - expansion.center)
+            }
+            _ => pairwise_acceleration(pos, &self.bodies),
+        }
+    }
+
+    fn cell(pos: Vec2) -> IVec2 {
+        (pos / GRAVITY_GRID_CELL_SIZE).floor().as_ivec2()
+    }
+
+    fn expand(&self, center: Vec2) -> Option<CellExpansion> {
+        let mut acceleration = Vec2::ZERO;
+        let mut jacobian = Mat2::ZERO;
+        for &(opos, omass) in self.bodies.iter() {
+            let d = opos - center;
+            let r = d.length();
+            if r < GRAVITY_GRID_MIN_BODY_DISTANCE {
+                return None;
+            }
+            // a(p) = d * k(r) with d = body - p and k(r) = G * m / r^2.6
+            let k = gravity_formula(r, omass) / r;
+            acceleration += d * k;
+            jacobian +=
+                Mat2::from_cols(d * d.x, d * d.y) * (2.6 * k / (r * r)) - Mat2::IDENTITY * k;
+        }
+        Some(CellExpansion {
+            center,
+            acceleration,
+            jacobian,
+        })
+    }
+}
+
 pub fn update(
     time: Res<Time>,
+    mut field: ResMut<GravityField>,
     attracting_bodies: Query<(Entity, &ColliderMassProperties, &Transform), With<AttractingBody>>,
     mut affected_bodies: Query<(&mut Velocity, &Transform, &mut AffectedByGravity)>,
 ) {
@@ -32,66 +132,528 @@ pub fn update(
             error!("Attracting entity {:?} has a ColliderMassProperties that is not of the Mass variant. Can't compute gravity.", entity);
         }
     }
-    for (
-        mut velocity,
-        Transform {
-            translation: pos, ..
-        },
-        mut feedback,
-    ) in affected_bodies.iter_mut()
-    {
-        let mut acceleration = Vec2::ZERO;
-        for &(opos, omass) in attracting_pos_mass.iter() {
-            let d = opos - pos.xy();
-            acceleration += d.normalize_or_zero() * gravity_formula(d.length(), omass);
-        }
-        feedback.last_acceleration = acceleration;
-        velocity.linvel += acceleration * time.delta_seconds();
-    }
+    *field = GravityField::new(attracting_pos_mass);
+    field.cache_cells(
+        affected_bodies
+            .iter()
+            .map(|(_, transform, _)| transform.translation.xy()),
+    );
+
+    let field = &*field;
+    let dt = time.delta_seconds();
+    affected_bodies
+        .par_iter_mut()
+        .for_each(|(mut velocity, transform, mut feedback)| {
+            let acceleration = field.acceleration(transform.translation.xy());
+            feedback.last_acceleration = acceleration;
+            velocity.linvel += acceleration * dt;
+        });
 }
 
 pub struct CoursePlanning {
-    pub path: Vec<(Vec2, f32)>, // (pos, distance to nearest object)
+    pub path: Vec<(Vec2, f32)>, // (pos, distance to nearest object), one point every `step_dt`
     pub closest_flyby: f32,
+    pub impact: Option<PlannedImpact>,
+    pub velocity: Vec2, // at the last point of the path
+}
+
+#[derive(Clone)]
+pub struct PlannedShip {
+    pub start_time: f32, // seconds after the bodies' current state
+    pub pos: Vec2,
+    pub velocity: Vec2,
+    pub radius: f32,
+    pub thrust: Option<ThrustProfile>, // None for a coasting ship
 }
 
+pub struct PlannedImpact {
+    pub time: f32,   // seconds from the start of the planning
+    pub body: usize, // index in the planned bodies
+}
+
+// Velocity Verlet, with each `step_dt` interval split in adaptive substeps.
+// Substeps shrink until the estimated position error of each substep is below `tolerance` (world units),
+// and so that the ship never travels more than a fraction of its distance to the nearest body in one substep.
+// Each substep is swept against the bodies' motion, so impacts between two path points are not missed.
 pub fn plan_course(
     max_dt: f32,
     step_dt: f32,
-    mut pos: Vec2,
-    mut velocity: Vec2,
+    tolerance: f32,
+    ship: &PlannedShip,
     bodies: &[(f32, f32, CircularOrbitChain)], // (mass, radius, orbit)
 ) -> CoursePlanning {
+    let (mut pos, mut velocity, ship_radius) = (ship.pos, ship.velocity, ship.radius);
+    let thrust_at = |t: f32| {
+        ship.thrust
+            .as_ref()
+            .map_or(Vec2::ZERO, |thrust| thrust.acceleration(t))
+    };
     let mut t = 0.0;
     let mut path = vec![];
     let mut closest_flyby = f32::INFINITY;
-    while t < max_dt {
-        let mut acceleration = Vec2::ZERO;
-        let mut closest_body_distance_at_step = f32::INFINITY;
-
-        for (m, r, orbit) in bodies.iter() {
-            let body_pos_at_time = orbit.pos(t);
-            let d = body_pos_at_time - pos;
-            if (d.length() - r) < closest_body_distance_at_step {
-                closest_body_distance_at_step = d.length() - r;
+    let mut impact = None;
+    let mut bodies_pos = Vec::with_capacity(bodies.len());
+    let mut next_bodies_pos = Vec::with_capacity(bodies.len());
+    let t0 = ship.start_time;
+    let (mut acceleration, mut nearest) = acceleration_at(pos, t0, bodies, &mut bodies_pos);
+    acceleration += thrust_at(0.0);
+    let mut nearest_body_speed = 0.0;
+    let mut substep = step_dt;
+    // The last interval is shorter if `max_dt` isn't a multiple of `step_dt`.
+    let steps = (max_dt / step_dt - 1e-3).ceil() as u32;
+    for step in 1..=steps {
+        let interval_end = (step as f32 * step_dt).min(max_dt);
+        let mut closest_body_distance_at_step = nearest.surface;
+        let mut substeps = 0;
+        while t < interval_end && impact.is_none() {
+            let closing_speed = velocity.length() + nearest_body_speed;
+            let proximity_limit = PLAN_PROXIMITY_FRACTION * nearest.center / closing_speed.max(1.0);
+            let mut h = substep
+                .min(proximity_limit)
+                .min(interval_end - t)
+                .max(PLAN_MIN_SUBSTEP_DT);
+            if substeps >= PLAN_MAX_SUBSTEPS_PER_STEP {
+                // We've spent our budget on this interval, finish it in one go.
+                h = interval_end - t;
+            }
+            loop {
+                let next_pos = pos + velocity * h + acceleration * (h * h / 2.0);
+                let (next_acceleration, next_nearest) =
+                    acceleration_at(next_pos, t0 + t + h, bodies, &mut next_bodies_pos);
+                let next_acceleration = next_acceleration + thrust_at(t + h);
+                let error = h * h * (next_acceleration - acceleration).length() / 6.0;
+                if error > tolerance
+                    && h > PLAN_MIN_SUBSTEP_DT
+                    && substeps < PLAN_MAX_SUBSTEPS_PER_STEP
+                {
+                    h = (h / 2.0).max(PLAN_MIN_SUBSTEP_DT);
+                    continue;
+                }
+                if let Some((s, body)) = sweep_bodies(
+                    pos,
+                    next_pos,
+                    &bodies_pos,
+                    &next_bodies_pos,
+                    ship_radius,
+                    bodies,
+                ) {
+                    pos = pos.lerp(next_pos, s);
+                    impact = Some(PlannedImpact {
+                        time: t + s * h,
+                        body,
+                    });
+                    break;
+                }
+                velocity += (acceleration + next_acceleration) * (h / 2.0);
+                pos = next_pos;
+                acceleration = next_acceleration;
+                nearest = next_nearest;
+                if let Some(i) = nearest.index {
+                    nearest_body_speed = bodies_pos[i].distance(next_bodies_pos[i]) / h;
+                }
+                std::mem::swap(&mut bodies_pos, &mut next_bodies_pos);
+                t += h;
+                substeps += 1;
+                // Grow or shrink the next substep according to how far we are from the tolerance.
+                let scale = if error > 0.0 {
+                    (tolerance / error).powf(1.0 / 3.0).clamp(0.5, 2.0)
+                } else {
+                    2.0
+                };
+                substep = (h * scale).clamp(PLAN_MIN_SUBSTEP_DT, step_dt);
+                break;
             }
-            acceleration += d.normalize() * gravity_formula(d.length(), *m);
+            closest_body_distance_at_step = closest_body_distance_at_step.min(nearest.surface);
+        }
+        if impact.is_some() {
+            closest_body_distance_at_step = 0.0;
         }
-
-        velocity += acceleration * step_dt;
-        pos += velocity * step_dt;
         path.push((pos, closest_body_distance_at_step));
-        t += step_dt;
 
         if closest_body_distance_at_step < closest_flyby {
             closest_flyby = closest_body_distance_at_step;
         }
-        if closest_flyby <= 0.0 {
+        if impact.is_some() {
             break;
         }
     }
     CoursePlanning {
         path,
         closest_flyby,
+        impact,
+        velocity,
+    }
+}
+
+pub fn gravity_at(pos: Vec2, t: f32, bodies: &[(f32, f32, CircularOrbitChain)]) -> Vec2 {
+    acceleration_at(pos, t, bodies, &mut Vec::with_capacity(bodies.len())).0
+}
+
+struct NearestBody {
+    index: Option<usize>,
+    center: f32,
+    surface: f32,
+}
+
+fn acceleration_at(
+    pos: Vec2,
+    t: f32,
+    bodies: &[(f32, f32, CircularOrbitChain)],
+    bodies_pos: &mut Vec<Vec2>,
+) -> (Vec2, NearestBody) {
+    let mut acceleration = Vec2::ZERO;
+    let mut nearest = NearestBody {
+        index: None,
+        center: f32::INFINITY,
+        surface: f32::INFINITY,
+    };
+    bodies_pos.clear();
+    for (i, (m, r, orbit)) in bodies.iter().enumerate() {
+        let body_pos = orbit.pos(t);
+        bodies_pos.push(body_pos);
+        let d = body_pos - pos;
+        let distance = d.length();
+        if distance - r < nearest.surface {
+            nearest = NearestBody {
+                index: Some(i),
+                center: distance,
+                surface: distance - r,
+            };
+        }
+        acceleration += d.normalize_or_zero() * gravity_formula(distance, *m);
+    }
+    (acceleration, nearest)
+}
+
+// Earliest contact between the ship moving from `start` to `end` and the bodies moving from `bodies_start` to `bodies_end`.
+// Returns the fraction of the segment at which the contact happens, and the body index.
+fn sweep_bodies(
+    start: Vec2,
+    end: Vec2,
+    bodies_start: &[Vec2],
+    bodies_end: &[Vec2],
+    ship_radius: f32,
+    bodies: &[(f32, f32, CircularOrbitChain)],
+) -> Option<(f32, usize)> {
+    bodies
+        .iter()
+        .enumerate()
+        .filter_map(|(i, (_, r, _))| {
+            sweep_circle(
+                start - bodies_start[i],
+                end - bodies_end[i],
+                r + ship_radius,
+            )
+            .map(|s| (s, i))
+        })
+        .min_by(|a, b| a.0.total_cmp(&b.0))
+}
+
+// Both ends are relative to the circle's center, which is assumed to move linearly over the segment.
+fn sweep_circle(start: Vec2, end: Vec2, radius: f32) -> Option<f32> {
+    let c = start.length_squared() - radius * radius;
+    if c <= 0.0 {
+        return Some(0.0);
+    }
+    let d = end - start;
+    let a = d.length_squared();
+    let b = 2.0 * start.dot(d);
+    let discriminant = b * b - 4.0 * a * c;
+    if a == 0.0 || discriminant < 0.0 {
+        return None;
+    }
+    let s = (-b - discriminant.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&s).then_some(s)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use bevy::{prelude::*, tasks::ComputeTaskPool, tasks::TaskPool};
+    use bevy_rapier2d::{dynamics::Velocity, geometry::ColliderMassProperties};
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    use super::{
+        gravity_formula, pairwise_acceleration, plan_course, AffectedByGravity, AttractingBody,
+        GravityField, PlannedShip,
+    };
+    use crate::{
+        celestial_body::{CircularOrbitChain, CircularOrbitDef},
+        thruster::Thruster,
+    };
+
+    const TOLERANCE: f32 = 0.01;
+
+    // Roughly the bodies of the default star system, with the binary pair and moons nearby.
+    fn star_system_bodies() -> Vec<(Vec2, f32)> {
+        vec![
+            (Vec2::new(-10000.0, -10000.0), 5e7),
+            (Vec2::new(0.0, 100000.0), 7e7),
+            (Vec2::new(0.0, 50000.0), 5e7),
+            (Vec2::new(70000.0, -70000.0), 5e7),
+            (Vec2::new(30000.0, -10000.0), 1e7),
+            (Vec2::new(30000.0, 0.0), 4e6),
+            (Vec2::new(-89200.0, -10000.0), 3e6),
+            (Vec2::new(-90800.0, -10000.0), 3e6),
+        ]
+    }
+
+    #[test]
+    fn empty_field_has_no_acceleration() {
+        let mut field = GravityField::new(vec![]);
+        field.cache_cells([Vec2::new(12.0, 34.0)].into_iter());
+        assert_eq!(field.acceleration(Vec2::new(12.0, 34.0)), Vec2::ZERO);
+    }
+
+    #[test]
+    fn field_matches_pairwise_sum() {
+        let mut rng = StdRng::seed_from_u64(4);
+        let mut bodies = star_system_bodies();
+        for _ in 0..50 {
+            bodies.push((
+                Vec2::new(
+                    rng.gen_range(-100000.0..100000.0),
+                    rng.gen_range(-100000.0..100000.0),
+                ),
+                rng.gen_range(1e6..1e8),
+            ));
+        }
+        for bodies in [star_system_bodies(), bodies] {
+            let positions = (0..10000)
+                .map(|_| {
+                    Vec2::new(
+                        rng.gen_range(-150000.0..150000.0),
+                        rng.gen_range(-150000.0..150000.0),
+                    )
+                })
+                .chain(bodies.iter().map(|&(pos, _)| pos + Vec2::new(600.0, 0.0)))
+                .collect::<Vec<Vec2>>();
+            let mut field = GravityField::new(bodies.clone());
+            field.cache_cells(positions.iter().copied());
+            for &pos in positions.iter() {
+                let exact = pairwise_acceleration(pos, &bodies);
+                let approx = field.acceleration(pos);
+                // Pulls from opposite sides can cancel out, so the error is measured
+                // against the magnitude of the individual contributions.
+                let scale = bodies
+                    .iter()
+                    .map(|body| pairwise_acceleration(pos, &[*body]).length())
+                    .sum::<f32>();
+                assert!(
+                    (exact - approx).length() <= scale * TOLERANCE,
+                    "at {:?}: {:?} != {:?}",
+                    pos,
+                    exact,
+                    approx
+                );
+            }
+        }
+    }
+
+    fn static_body(pos: Vec2, mass: f32, radius: f32) -> (f32, f32, CircularOrbitChain) {
+        (
+            mass,
+            radius,
+            CircularOrbitChain {
+                origin: pos,
+                chain: vec![],
+            },
+        )
+    }
+
+    fn coasting(pos: Vec2, velocity: Vec2, radius: f32) -> PlannedShip {
+        PlannedShip {
+            start_time: 0.0,
+            pos,
+            velocity,
+            radius,
+            thrust: None,
+        }
+    }
+
+    #[test]
+    fn planned_circular_orbit_keeps_its_radius() {
+        let (mass, radius) = (5e7, 3000.0);
+        let speed = (gravity_formula(radius, mass) * radius).sqrt();
+        let bodies = [static_body(Vec2::ZERO, mass, 500.0)];
+        let plan = plan_course(
+            30.0,
+            0.05,
+            0.5,
+            &coasting(Vec2::new(radius, 0.0), Vec2::new(0.0, speed), 32.0),
+            &bodies,
+        );
+        assert_eq!(plan.path.len(), 600);
+        for (pos, _) in plan.path.iter() {
+            assert!(
+                (pos.length() - radius).abs() < radius * TOLERANCE,
+                "drifted to {:?}",
+                pos
+            );
+        }
+    }
+
+    #[test]
+    fn fast_flyby_between_steps_is_detected() {
+        // At 4000 u/s, two consecutive 0.5s steps land on both sides of the body.
+        let bodies = [static_body(Vec2::ZERO, 1.0, 500.0)];
+        let plan = plan_course(
+            10.0,
+            0.5,
+            5.0,
+            &coasting(Vec2::new(-1300.0, 0.0), Vec2::new(4000.0, 0.0), 0.0),
+            &bodies,
+        );
+        assert!(plan.closest_flyby <= 0.0);
+        assert_eq!(plan.path.len(), 1);
+        let impact = plan.impact.expect("the flyby should be an impact");
+        assert_eq!(impact.body, 0);
+        assert!(
+            (impact.time - 800.0 / 4000.0).abs() < 0.01,
+            "impact at {}",
+            impact.time
+        );
+    }
+
+    #[test]
+    fn orbiting_body_hitting_a_still_ship_is_detected() {
+        let orbit = CircularOrbitChain {
+            origin: Vec2::ZERO,
+            chain: vec![CircularOrbitDef {
+                theta: -1.0,
+                radius: 1000.0,
+                freq: 1.0,
+            }
+            .into()],
+        };
+        let plan = plan_course(
+            10.0,
+            0.5,
+            5.0,
+            &coasting(Vec2::new(1000.0, 0.0), Vec2::ZERO, 0.0),
+            &[(1.0, 100.0, orbit)],
+        );
+        // The body's edge reaches the ship once its center is 100 away, 2 * asin(100 / 2000) radians early.
+        let expected = 1.0 - 2.0 * (100.0f32 / 2000.0).asin();
+        let impact = plan.impact.expect("the body should hit the ship");
+        assert!(
+            (impact.time - expected).abs() < 0.01,
+            "impact at {}, expected {}",
+            impact.time,
+            expected
+        );
+    }
+
+    #[test]
+    fn burning_in_empty_space_follows_the_thrust_profile() {
+        let thruster = Thruster {
+            max_thrust: 8.0,
+            current_thrust: 8.0,
+            rampup_rate: 0.0,
+            shutoff_rate: 8.0,
+            ignition_thrust: 3.0,
+        };
+        let thrust = thruster.profile(Vec2::X, 4.0, 2.0);
+        let plan = plan_course(
+            4.0,
+            0.05,
+            0.5,
+            &PlannedShip {
+                thrust: Some(thrust),
+                ..coasting(Vec2::ZERO, Vec2::ZERO, 32.0)
+            },
+            &[],
+        );
+        // Constant acceleration for 2s, then a linear shutoff over 1s, then coasting.
+        let a = thrust.acceleration(0.0).x;
+        let (x_burn, v_burn) = (a * 2.0 * 2.0 / 2.0, a * 2.0);
+        let (x_shutoff, v_shutoff) = (x_burn + v_burn + a / 3.0, v_burn + a / 2.0);
+        let expected = x_shutoff + v_shutoff;
+        let (end, _) = plan.path.last().unwrap();
+        assert!(
+            (end.x - expected).abs() < expected * TOLERANCE,
+            "{} != {}",
+            end.x,
+            expected
+        );
+    }
+
+    fn pairwise_update(
+        time: Res<Time>,
+        attracting_bodies: Query<(&ColliderMassProperties, &Transform), With<AttractingBody>>,
+        mut affected_bodies: Query<(&mut Velocity, &Transform, &mut AffectedByGravity)>,
+    ) {
+        let bodies = attracting_bodies
+            .iter()
+            .filter_map(|(mass_props, t)| match mass_props {
+                &ColliderMassProperties::Mass(m) => Some((t.translation.xy(), m)),
+                _ => None,
+            })
+            .collect::<Vec<(Vec2, f32)>>();
+        for (mut velocity, transform, mut feedback) in affected_bodies.iter_mut() {
+            let acceleration = pairwise_acceleration(transform.translation.xy(), &bodies);
+            feedback.last_acceleration = acceleration;
+            velocity.linvel += acceleration * time.delta_seconds();
+        }
+    }
+
+    // Compares the previous serial O(bodies x affected) pass with the current one.
+    #[test]
+    #[ignore = "benchmark: cargo test --release bench_gravity -- --ignored --nocapture"]
+    fn bench_gravity() {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut world = World::new();
+        world.insert_resource(Time::<()>::default());
+        world.init_resource::<GravityField>();
+        for (pos, mass) in star_system_bodies() {
+            world.spawn((
+                AttractingBody,
+                ColliderMassProperties::Mass(mass),
+                Transform::from_translation(pos.extend(0.0)),
+            ));
+        }
+        // Ships and their lasers fly in waves of a few hundred entities.
+        let waves = (0..40)
+            .map(|_| {
+                Vec2::new(
+                    rng.gen_range(-100000.0..100000.0),
+                    rng.gen_range(-100000.0..100000.0),
+                )
+            })
+            .collect::<Vec<Vec2>>();
+        for i in 0..20000 {
+            let pos = waves[i % waves.len()]
+                + Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                    * rng.gen_range(50.0..2000.0);
+            world.spawn((
+                Velocity::zero(),
+                Transform::from_translation(pos.extend(0.0)),
+                AffectedByGravity::default(),
+            ));
+        }
+
+        let iterations = 100;
+        let mut timings = vec![];
+        for system in [
+            IntoSystemConfigs::into_configs(pairwise_update),
+            IntoSystemConfigs::into_configs(super::update),
+        ] {
+            let mut schedule = Schedule::default();
+            schedule.add_systems(system);
+            schedule.run(&mut world);
+            let start = Instant::now();
+            for _ in 0..iterations {
+                schedule.run(&mut world);
+            }
+            timings.push(start.elapsed().as_secs_f64() * 1000.0 / iterations as f64);
+        }
+        println!(
+            "gravity pass, 20000 affected entities: pairwise {:.3} ms, field {:.3} ms, speedup x{:.2}",
+            timings[0],
+            timings[1],
+            timings[0] / timings[1]
+        );
     }
 }
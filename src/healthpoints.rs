@@ -2,14 +2,37 @@ use bevy::prelude::*;
 
 use crate::ui::Difficulty;
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DamageCause {
+    Laser,
+    Ramming, // collided with another ship
+    Crash,   // collided with a celestial body
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Hit {
+    pub cause: DamageCause,
+    pub by: Option<Entity>, // the shooter, the other ship, or the body
+}
+
 #[derive(Component)]
 pub struct HealthPoints {
     pub max: f32,
     pub current: f32,
+    pub last_hit: Option<Hit>,
 }
 
 impl HealthPoints {
-    pub fn decrease(&mut self, amount: f32, difficulty: Difficulty) {
+    pub fn new(max: f32) -> Self {
+        Self {
+            max,
+            current: max,
+            last_hit: None,
+        }
+    }
+
+    // Returns the damage actually dealt.
+    pub fn decrease(&mut self, amount: f32, difficulty: Difficulty, hit: Hit) -> f32 {
         let damage_multiplier = match difficulty {
             Difficulty::GodMode => 0.0,
             Difficulty::Easy => 0.25,
@@ -18,6 +41,15 @@ impl HealthPoints {
             Difficulty::Impossible => 3.0,
         };
 
+        let before = self.current;
         self.current = (self.current - (amount * damage_multiplier)).max(0.0);
+        if amount > 0.0 {
+            self.last_hit = Some(hit);
+        }
+        before - self.current
+    }
+
+    pub fn heal(&mut self, amount: f32) {
+        self.current = (self.current + amount).min(self.max);
     }
 }
@@ -3,12 +3,13 @@ use std::{collections::HashMap, ops::Add};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::ExternalImpulse;
 
-use crate::system_sets::AppStage;
+use crate::{simulation::SimulationMode, system_sets::AppStage};
 
 pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
     app.init_resource::<Events<AddExternalImpulse>>(); // Override Bevy's automatic event's cleanup. We drain these events ourselves.
     app.add_systems(
-        Update,
+        schedule,
         drain_added_external_impulses.in_set(AppStage::AggregateImpulses),
     );
 }
@@ -0,0 +1,252 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::{RigidBody, Velocity},
+    geometry::{Collider, ColliderMassProperties},
+};
+
+use crate::{
+    celestial_body::{self, CelestialBodyMarker, CircularOrbitChain},
+    collisions_handler::ShipHitBody,
+    course_planner::{scaled_radius, ComputedTrajectory, PlanningTask},
+    gravity::{self, gravity_potential, AffectedByGravity},
+    healthpoints::HealthPoints,
+    impulses_aggregator::AddExternalImpulse,
+    maneuver::Autopilot,
+    player::PlayerMarker,
+    simulation::SimulationMode,
+    system_sets::AppStage,
+    thruster::Thruster,
+    ui::{Difficulty, GameSettings},
+    AppState,
+};
+
+// Touching down slower than these, towards and along the surface, docks the ship.
+const LANDING_MAX_NORMAL_SPEED: f32 = 400.0;
+const LANDING_MAX_TANGENTIAL_SPEED: f32 = 300.0;
+// The ship can't land on its nose: its heading must be within this angle of the surface's normal.
+const LANDING_MAX_TILT: f32 = PI * 5.0 / 12.0;
+const LANDED_REPAIR_HP_PER_S: f32 = 10.0;
+// Surface gravity is much stronger than the engine, so take-offs are catapulted.
+// A vertical launch peaks at this many times the body's radius.
+const LAUNCH_APEX_RADIUS_FACTOR: f32 = 4.0;
+const LAUNCH_CLEARANCE: f32 = 4.0;
+
+// The player sits on a body's surface and moves along with it.
+#[derive(Component)]
+pub struct Landed {
+    pub body: Entity,
+    offset: Vec2, // from the body's center
+}
+
+type CelestialBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        &'static Transform,
+        &'static Collider,
+        &'static ColliderMassProperties,
+        &'static CircularOrbitChain,
+    ),
+    (With<CelestialBodyMarker>, Without<PlayerMarker>),
+>;
+
+type TouchingDownPlayer<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    &'a Collider,
+    &'a AffectedByGravity,
+);
+
+type LandedPlayer<'a> = (
+    Entity,
+    &'a mut Transform,
+    &'a mut Velocity,
+    &'a mut HealthPoints,
+    &'a Thruster,
+    &'a Landed,
+);
+
+pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
+    app.add_systems(
+        schedule,
+        (follow_body, take_off)
+            .chain()
+            .after(gravity::update)
+            .after(celestial_body::update)
+            .in_set(AppStage::Simulation)
+            .run_if(in_state(AppState::Game)),
+    );
+}
+
+pub enum Touchdown {
+    Landing,
+    // The impact's damage is dealt by `collisions_handler::impact_damage_on_ships`.
+    Crash,
+}
+
+// `normal` points from the body's center to the ship, `heading` is where the ship's nose points.
+pub fn touchdown(relative_velocity: Vec2, normal: Vec2, heading: Vec2) -> Touchdown {
+    // The contact may already have bounced the ship back, so only the magnitude of the normal speed matters.
+    let normal_speed = relative_velocity.dot(normal).abs();
+    let tangential_speed = relative_velocity.perp_dot(normal).abs();
+    if normal_speed <= LANDING_MAX_NORMAL_SPEED
+        && tangential_speed <= LANDING_MAX_TANGENTIAL_SPEED
+        && heading.angle_between(normal).abs() <= LANDING_MAX_TILT
+    {
+        Touchdown::Landing
+    } else {
+        Touchdown::Crash
+    }
+}
+
+// The speed at which a vertical launch from `surface` peaks at `apex`.
+pub fn launch_speed(mass: f32, surface: f32, apex: f32) -> f32 {
+    (2.0 * (gravity_potential(apex, mass) - gravity_potential(surface, mass)))
+        .max(0.0)
+        .sqrt()
+}
+
+pub fn touch_down(
+    mut commands: Commands,
+    mut hits: EventReader<ShipHitBody>,
+    player: Query<TouchingDownPlayer, (With<PlayerMarker>, Without<Landed>)>,
+    bodies: CelestialBodies,
+    mut impulses: EventWriter<AddExternalImpulse>,
+    settings: Res<GameSettings>,
+) {
+    for &ShipHitBody {
+        ship: player_entity,
+        body: body_entity,
+        ..
+    } in hits.read()
+    {
+        let Ok((_, pt, pv, player_collider, gravity)) = player.get(player_entity) else {
+            continue;
+        };
+        let Ok((bt, body_collider, _, orbit)) = bodies.get(body_entity) else {
+            continue;
+        };
+        let normal = (pt.translation.xy() - bt.translation.xy()).normalize_or_zero();
+        match touchdown(pv.linvel - orbit.velocity(), normal, pt.up().xy()) {
+            Touchdown::Landing => {
+                let altitude =
+                    scaled_radius(body_collider, bt) + scaled_radius(player_collider, pt);
+                commands
+                    .entity(player_entity)
+                    .remove::<(Autopilot, PlanningTask)>()
+                    .insert((
+                        Landed {
+                            body: body_entity,
+                            offset: normal * altitude,
+                        },
+                        RigidBody::KinematicPositionBased,
+                        // Nothing to plan while we're sitting on the ground.
+                        ComputedTrajectory::default(),
+                    ));
+            }
+            Touchdown::Crash => {
+                if settings.difficulty == Difficulty::GodMode {
+                    impulses.send(AddExternalImpulse {
+                        entity: player_entity,
+                        impulse: (gravity.last_acceleration * -0.4)
+                            .rotate(Vec2::from_angle(PI / 4.)),
+                        torque_impulse: 0.,
+                    });
+                }
+            }
+        }
+    }
+}
+
+// Throttling up with the nose pointing away from the ground launches the ship.
+fn take_off(
+    mut commands: Commands,
+    mut player: Query<LandedPlayer, With<PlayerMarker>>,
+    bodies: CelestialBodies,
+) {
+    let Ok((entity, mut t, mut v, _, thruster, landed)) = player.get_single_mut() else {
+        return;
+    };
+    let Ok((bt, collider, &ColliderMassProperties::Mass(mass), orbit)) = bodies.get(landed.body)
+    else {
+        return;
+    };
+    let heading = t.up().xy();
+    if thruster.current_thrust <= 0.0 || heading.dot(landed.offset) <= 0.0 {
+        return;
+    }
+    let surface = scaled_radius(collider, bt);
+    let speed = launch_speed(mass, surface, surface * LAUNCH_APEX_RADIUS_FACTOR);
+    let offset = landed.offset + landed.offset.normalize_or_zero() * LAUNCH_CLEARANCE;
+    t.translation = (bt.translation.xy() + offset).extend(t.translation.z);
+    v.linvel = orbit.velocity() + heading * speed;
+    commands
+        .entity(entity)
+        .remove::<Landed>()
+        .insert(RigidBody::Dynamic);
+}
+
+fn follow_body(
+    time: Res<Time>,
+    mut player: Query<LandedPlayer, With<PlayerMarker>>,
+    bodies: CelestialBodies,
+) {
+    for (_, mut t, mut v, mut hp, _, landed) in player.iter_mut() {
+        if let Ok((bt, _, _, orbit)) = bodies.get(landed.body) {
+            t.translation = (bt.translation.xy() + landed.offset).extend(t.translation.z);
+            v.linvel = orbit.velocity();
+            v.angvel = 0.0;
+        }
+        // Landing is also the only way to get repaired.
+        hp.heal(LANDED_REPAIR_HP_PER_S * time.delta_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{launch_speed, touchdown, Touchdown};
+    use crate::gravity::gravity_potential;
+
+    #[test]
+    fn only_gentle_touchdowns_land() {
+        let up = Vec2::Y;
+        assert!(matches!(
+            touchdown(Vec2::new(200.0, -100.0), up, up),
+            Touchdown::Landing
+        ));
+        // Skimming the surface, however slowly we sink towards it.
+        assert!(matches!(
+            touchdown(Vec2::new(2000.0, -100.0), up, up),
+            Touchdown::Crash
+        ));
+        // Diving in.
+        assert!(matches!(
+            touchdown(Vec2::new(0.0, -1000.0), up, up),
+            Touchdown::Crash
+        ));
+        // Bounced back up already.
+        assert!(matches!(
+            touchdown(Vec2::new(0.0, 300.0), up, up),
+            Touchdown::Landing
+        ));
+        // Nose first.
+        assert!(matches!(
+            touchdown(Vec2::new(0.0, -100.0), up, -up),
+            Touchdown::Crash
+        ));
+    }
+
+    #[test]
+    fn launch_reaches_the_apex() {
+        let (mass, surface, apex) = (4e6, 250.0, 1000.0);
+        let v = launch_speed(mass, surface, apex);
+        let energy_at_surface = v * v / 2.0 + gravity_potential(surface, mass);
+        assert!((energy_at_surface - gravity_potential(apex, mass)).abs() < 1.0);
+    }
+}
@@ -3,7 +3,10 @@ use bevy_rapier2d::{
     dynamics::{Ccd, RigidBody, Velocity},
     geometry::{ActiveEvents, Collider, ColliderMassProperties, Sensor},
 };
-use bevy_vector_shapes::{painter::ShapePainter, shapes::RectPainter};
+use bevy_vector_shapes::{
+    painter::ShapePainter,
+    shapes::{DiscPainter, RectPainter},
+};
 
 use crate::{
     camera::{game_layer, GameCameraMarker, UI_LAYER},
@@ -13,6 +16,9 @@ use crate::{
 };
 
 pub const LASER_LIFETIME_S: f32 = 2.0;
+const MINE_RADIUS: f32 = 16.0;
+// Fixed-point iterations folding the target's acceleration into the lead.
+const LEAD_REFINEMENTS: u32 = 3;
 
 #[derive(Component)]
 pub struct LaserAbility {
@@ -36,17 +42,29 @@ pub enum LaserOrigin {
 #[derive(Component)]
 pub struct Laser {
     pub origin: LaserOrigin,
+    pub shooter: Entity,
     pub damage: f32,
     pub shot_at: f32,
+    pub lifetime: f32,
 }
 
+// A laser that drifts along its orbit instead of flying, until something runs into it.
+#[derive(Component)]
+pub struct Mine;
+
 pub fn update(
     mut despawn_queue: ResMut<DespawnQueue>,
     time: Res<Time>,
     query: Query<(Entity, &Laser)>,
 ) {
-    for (entity, Laser { shot_at, .. }) in query.iter() {
-        if time.elapsed_seconds() - shot_at > LASER_LIFETIME_S {
+    for (
+        entity,
+        Laser {
+            shot_at, lifetime, ..
+        },
+    ) in query.iter()
+    {
+        if time.elapsed_seconds() - shot_at > *lifetime {
             despawn_queue.1.insert(entity);
         }
     }
@@ -56,12 +74,12 @@ pub fn draw(
     time: Res<Time>,
     player: Query<(&Transform, &Velocity), With<PlayerMarker>>,
     camera: Query<(&Transform, &OrthographicProjection), With<GameCameraMarker>>,
-    query: Query<(&Transform, &Velocity, &Laser)>,
+    query: Query<(&Transform, &Velocity, &Laser, Has<Mine>)>,
     mut painter: ShapePainter,
 ) {
     if let Ok((pt, pv)) = player.get_single() {
         if let Ok((_cam_transform, cam_proj)) = camera.get_single() {
-            for (transform, v, Laser { origin, .. }) in query.iter() {
+            for (transform, v, Laser { origin, .. }, is_mine) in query.iter() {
                 let dp = (transform.translation
                     - pt.translation
                     - pv.linvel.extend(0.0) * time.delta_seconds())
@@ -74,6 +92,12 @@ pub fn draw(
                 painter.reset();
                 painter.set_2d();
                 painter.render_layers = Some(RenderLayers::layer(UI_LAYER));
+                if is_mine {
+                    painter.set_translation(dp);
+                    painter.color = Color::hex("FF8000").unwrap();
+                    painter.circle(MINE_RADIUS / cam_proj.scale);
+                    continue;
+                }
                 painter.set_rotation(Quat::from_axis_angle(Vec3::Z, dv.y.atan2(dv.x)));
                 painter.set_translation(dp);
                 painter.color = color;
@@ -83,6 +107,22 @@ pub fn draw(
     }
 }
 
+pub fn spawn_mine(commands: &mut Commands, position: Vec2, velocity: Vec2, props: Laser) {
+    commands.spawn((
+        props,
+        Mine,
+        TransformBundle::from_transform(Transform::from_translation(position.extend(1.0))),
+        RigidBody::KinematicVelocityBased,
+        Collider::ball(MINE_RADIUS),
+        ColliderMassProperties::Mass(1.0),
+        Velocity::linear(velocity),
+        ActiveEvents::COLLISION_EVENTS,
+        Sensor,
+        AffectedByGravity::default(),
+        game_layer(),
+    ));
+}
+
 pub fn spawn(commands: &mut Commands, position: Vec2, velocity: Vec2, props: Laser) {
     let transform = Transform::from_translation(Vec3 {
         x: position.x,
@@ -107,3 +147,77 @@ pub fn spawn(commands: &mut Commands, position: Vec2, velocity: Vec2, props: Las
         game_layer(),
     ));
 }
+
+// Where to shoot a laser leaving at `speed` relative to the shooter, so that it meets the target, and when.
+// Everything is relative to the shooter, whose velocity the laser inherits. Gravity pulls on both alike,
+// so `relative_acceleration` is only the difference between what the target and the shooter feel.
+pub fn firing_solution(
+    relative_position: Vec2,
+    relative_velocity: Vec2,
+    relative_acceleration: Vec2,
+    speed: f32,
+) -> Option<(Vec2, f32)> {
+    // Solve |p + v t| = speed * t, ignoring the acceleration.
+    let a = relative_velocity.length_squared() - speed * speed;
+    let b = 2.0 * relative_position.dot(relative_velocity);
+    let c = relative_position.length_squared();
+    let mut t = if a.abs() < 1e-6 {
+        (b < 0.0).then(|| -c / b)?
+    } else {
+        let discriminant = b * b - 4.0 * a * c;
+        if discriminant < 0.0 {
+            return None;
+        }
+        let sqrt = discriminant.sqrt();
+        [(-b - sqrt) / (2.0 * a), (-b + sqrt) / (2.0 * a)]
+            .into_iter()
+            .filter(|&t| t >= 0.0)
+            .min_by(f32::total_cmp)?
+    };
+    let at =
+        |t: f32| relative_position + relative_velocity * t + relative_acceleration * (t * t / 2.0);
+    for _ in 0..LEAD_REFINEMENTS {
+        t = at(t).length() / speed;
+    }
+    Some((at(t), t))
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::firing_solution;
+
+    #[test]
+    fn lasers_lead_moving_targets() {
+        let speed = 1000.0;
+        // Crossing our line of sight: aim ahead of it.
+        let (aim, t) = firing_solution(
+            Vec2::new(1000.0, 0.0),
+            Vec2::new(0.0, 300.0),
+            Vec2::ZERO,
+            speed,
+        )
+        .unwrap();
+        assert!(aim.y > 0.0);
+        assert!((aim.length() - speed * t).abs() < 1e-2);
+        assert!((aim - Vec2::new(1000.0, 300.0 * t)).length() < 1e-2);
+        // Running away faster than the laser.
+        assert!(firing_solution(
+            Vec2::new(1000.0, 0.0),
+            Vec2::new(2000.0, 0.0),
+            Vec2::ZERO,
+            speed
+        )
+        .is_none());
+        // Falling towards a body we're not as close to.
+        let (aim, _) = firing_solution(
+            Vec2::new(1000.0, 0.0),
+            Vec2::ZERO,
+            Vec2::new(0.0, -100.0),
+            speed,
+        )
+        .unwrap();
+        assert!(aim.y < 0.0);
+    }
+}
@@ -7,13 +7,23 @@ mod collisions_handler;
 mod course_planner;
 mod death;
 mod despawn_queue;
+mod floating_origin;
 mod frame_pace;
+mod gameplay_events;
 mod gravity;
 mod healthpoints;
 mod impulses_aggregator;
+mod landing;
 mod lasers;
+mod maneuver;
+mod mothership;
+mod orbital_elements;
 mod particles;
 mod player;
+mod scoring;
+mod simulation;
+mod star_system;
+mod star_system_generator;
 mod system_sets;
 mod thruster;
 mod ui;
@@ -25,8 +35,9 @@ use bevy::{
     text::TextPlugin, time::TimePlugin, ui::UiPlugin, winit::WinitPlugin,
 };
 use bevy_parallax::ParallaxPlugin;
-use bevy_rapier2d::plugin::{NoUserData, RapierConfiguration, RapierPhysicsPlugin, TimestepMode};
+use bevy_rapier2d::plugin::{NoUserData, RapierConfiguration, RapierPhysicsPlugin};
 use bevy_vector_shapes::Shape2dPlugin;
+use simulation::SimulationMode;
 use system_sets::AppStage;
 
 pub const GLOBAL_IMPULSE_DURATION_MULT: f32 = 200.0;
@@ -42,6 +53,8 @@ pub enum AppState {
 
 fn main() {
     let mut app = App::new();
+    let simulation_mode = SimulationMode::from_args();
+    let simulation_schedule = simulation_mode.schedule();
 
     app.insert_resource(AssetMetaCheck::Never);
 
@@ -90,24 +103,34 @@ fn main() {
     app.add_plugins(AnimationPlugin);
     app.add_plugins(ParallaxPlugin);
     app.add_plugins(Shape2dPlugin::default());
-    app.add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0));
+    app.insert_resource(RapierConfiguration {
+        gravity: Vec2::ZERO,
+        timestep_mode: simulation_mode.timestep_mode(),
+        ..default()
+    });
+    app.add_plugins(
+        RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0)
+            .in_schedule(simulation_mode.physics_schedule()),
+    );
 
+    simulation::setup(&mut app, simulation_mode);
     camera::setup(&mut app);
     impulses_aggregator::setup(&mut app);
     despawn_queue::setup(&mut app);
     system_sets::setup(&mut app);
+    collisions_handler::setup(&mut app);
+    gameplay_events::setup(&mut app);
+    scoring::setup(&mut app);
     ui::setup(&mut app);
     frame_pace::setup(&mut app);
+    star_system::setup(&mut app);
+    gravity::setup(&mut app);
+    floating_origin::setup(&mut app);
+    landing::setup(&mut app);
+    maneuver::setup(&mut app);
+    mothership::setup(&mut app);
+    orbital_elements::setup(&mut app);
 
-    app.insert_resource(RapierConfiguration {
-        gravity: Vec2::ZERO,
-        timestep_mode: TimestepMode::Variable {
-            max_dt: 1.0 / 20.0,
-            time_scale: 1.0,
-            substeps: 2,
-        },
-        ..default()
-    });
     app.add_systems(
         OnExit(AppState::Game),
         (
@@ -120,52 +143,104 @@ fn main() {
 
     app.add_systems(
         OnEnter(AppState::Game),
-        (celestial_body::setup, alien_waves::setup, ai::setup).chain(),
+        (alien_waves::setup, ai::setup).chain(),
+    );
+    app.add_systems(
+        simulation_schedule,
+        (celestial_body::setup, player::setup)
+            .chain()
+            .run_if(in_state(AppState::Game)),
     );
-    app.add_systems(Update, (player::setup,).run_if(in_state(AppState::Game)));
 
     app.add_systems(
-        Update,
-        (player::control, alien_waves::update, alien_ship::update)
+        simulation_schedule,
+        (
+            (
+                maneuver::edit_nodes,
+                player::control,
+                maneuver::execute_node,
+            )
+                .chain(),
+            alien_waves::update,
+            alien_waves::launch_drones,
+            alien_ship::update,
+        )
             .in_set(AppStage::Control)
             .run_if(in_state(AppState::Game)),
     );
 
     app.add_systems(
-        Update,
-        (ai::update_ai_states, ai::update_ai_controllers)
+        simulation_schedule,
+        (
+            ai::pursuit::plan_pursuits,
+            ai::pursuit::apply_pursuits,
+            ai::update_ai_states,
+            ai::update_ai_controllers,
+        )
             .chain()
             .in_set(AppStage::AI)
             .run_if(in_state(AppState::Game)),
     );
 
     app.add_systems(
-        Update,
+        simulation_schedule,
         (
             course_planner::compute_player_trajectory,
             course_planner::compute_enemies_trajectories,
+            apply_deferred,
+            course_planner::apply_planned_trajectories,
         )
+            .chain()
             .in_set(AppStage::Trajectories)
             .run_if(in_state(AppState::Game)),
     );
     app.add_systems(
-        Update,
+        simulation_schedule,
         (
             thruster::update,
             lasers::update,
-            collisions_handler::update,
             gravity::update,
             celestial_body::update,
             death::update,
             particles::update,
             particles::thrusters::spawn_main_thruster_particles,
+            particles::sparks::spawn_sparks,
         )
             .in_set(AppStage::Simulation)
             .run_if(in_state(AppState::Game)),
     );
+    app.add_systems(
+        simulation_schedule,
+        (
+            collisions_handler::route_collisions,
+            (
+                // Hardpoints stick out of their mothership's hull, a laser reaches them first:
+                // one touching both in a tick only hits the hardpoint.
+                (
+                    collisions_handler::laser_hit_hardpoint,
+                    collisions_handler::laser_hit_ship,
+                )
+                    .chain(),
+                collisions_handler::laser_hit_body,
+                landing::touch_down,
+            ),
+            apply_deferred,
+            collisions_handler::impact_damage_on_ships,
+        )
+            .chain()
+            .after(gravity::update)
+            .after(celestial_body::update)
+            .in_set(AppStage::Simulation)
+            .run_if(in_state(AppState::Game)),
+    );
     app.add_systems(
         Update,
-        (lasers::draw, particles::draw, ui::radar::draw_radar_hud)
+        (
+            lasers::draw,
+            particles::draw,
+            ui::radar::draw_radar_hud.run_if(ui::map::map_closed),
+            ui::map::draw_map.run_if(not(ui::map::map_closed)),
+        )
             .chain()
             .in_set(AppStage::Draw)
             .run_if(in_state(AppState::Game)),
@@ -0,0 +1,421 @@
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+
+use crate::{
+    ai::orientation_controller::OrientationController,
+    celestial_body::CircularOrbitChain,
+    course_planner::ComputedTrajectory,
+    gravity::{gravity_at, plan_course, CoursePlanning, PlannedShip},
+    impulses_aggregator::AddExternalImpulse,
+    player::{PlayerMarker, PLAYER_MASS, ROTATION_IMPULSE},
+    thruster::Thruster,
+    AppState, GLOBAL_IMPULSE_DURATION_MULT,
+};
+
+const NODE_DEFAULT_LEAD_S: f32 = 10.0;
+const NODE_TIME_RATE: f32 = 4.0; // seconds per second, when sliding a node along the path
+const NODE_DELTA_V_RATE: f32 = 200.0; // delta-v per second, when tuning a node
+const PLAN_AFTER_LAST_NODE_S: f32 = 30.0;
+const AUTOPILOT_MAX_BURN_THETA: f32 = std::f32::consts::PI / 32.0;
+const PLAYER_ANGULAR_INERTIA: f32 = 0.5 * PLAYER_MASS * 48.0 * 48.0; // 32 radius ball collider, scaled by 1.5
+
+// A planned burn. The game is planar, so unlike the usual prograde/normal/radial frame there is no normal component.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ManeuverNode {
+    pub at: f32,       // elapsed game time
+    pub prograde: f32, // along the velocity at the node
+    pub radial: f32,   // across the velocity, away from the local gravity pull
+}
+
+#[derive(Resource, Default)]
+pub struct ManeuverPlan {
+    pub nodes: Vec<ManeuverNode>,
+    pub selected: usize,
+}
+
+impl ManeuverPlan {
+    // The nodes that are still ahead of us, in chronological order.
+    pub fn upcoming(&self, now: f32) -> Vec<ManeuverNode> {
+        let mut nodes = self
+            .nodes
+            .iter()
+            .filter(|node| node.at > now)
+            .copied()
+            .collect::<Vec<ManeuverNode>>();
+        nodes.sort_by(|a, b| a.at.total_cmp(&b.at));
+        nodes
+    }
+}
+
+// One-shot key presses, read every frame and consumed by the next simulation tick,
+// so that none is lost or repeated when frames and ticks don't line up.
+#[derive(Resource, Default)]
+pub struct ManeuverKeys {
+    toggle_autopilot: bool,
+    new_node: bool,
+    select_next: bool,
+    delete: bool,
+}
+
+// Present on the player while the autopilot flies the ship.
+#[derive(Component)]
+pub struct Autopilot {
+    orientation_controller: OrientationController,
+    burn: Option<AutopilotBurn>,
+}
+
+impl Autopilot {
+    pub fn burning(&self) -> bool {
+        self.burn.is_some()
+    }
+}
+
+impl Default for Autopilot {
+    fn default() -> Self {
+        Self {
+            orientation_controller: OrientationController::new(ROTATION_IMPULSE)
+                .with_angular_inertia(PLAYER_ANGULAR_INERTIA),
+            burn: None,
+        }
+    }
+}
+
+// Latched when the burn starts: the node's time passes during the burn, and the plan stops showing it.
+struct AutopilotBurn {
+    node: ManeuverNode,
+    direction: Vec2,
+    remaining_delta_v: f32,
+}
+
+type AutopilotedPlayer<'a> = (
+    Entity,
+    &'a Transform,
+    &'a Velocity,
+    &'a ComputedTrajectory,
+    &'a mut Thruster,
+    &'a mut Autopilot,
+);
+
+pub struct PlannedNode {
+    pub node: ManeuverNode,
+    pub pos: Vec2,
+    pub delta_v: Vec2, // in world space
+}
+
+pub struct PlannedManeuvers {
+    pub nodes: Vec<PlannedNode>,
+    pub course: CoursePlanning, // from the first node on
+}
+
+// Chains `plan_course` from node to node, applying each node's delta-v in between.
+// `nodes` must be upcoming nodes, sorted by time.
+pub fn plan_maneuvers(
+    now: f32,
+    step_dt: f32,
+    tolerance: f32,
+    ship: PlannedShip,
+    nodes: &[ManeuverNode],
+    bodies: &[(f32, f32, CircularOrbitChain)],
+) -> PlannedManeuvers {
+    let mut ship = ship;
+    let mut planned_nodes = vec![];
+    let mut course = CoursePlanning {
+        path: vec![],
+        closest_flyby: f32::INFINITY,
+        impact: None,
+        velocity: ship.velocity,
+    };
+    let legs_end = nodes
+        .iter()
+        .map(|node| node.at - now)
+        .chain(std::iter::once(
+            nodes.last().map_or(0.0, |node| node.at - now) + PLAN_AFTER_LAST_NODE_S,
+        ));
+    for (i, leg_end) in legs_end.enumerate() {
+        let leg = plan_course(leg_end - ship.start_time, step_dt, tolerance, &ship, bodies);
+        if let Some(&(pos, _)) = leg.path.last() {
+            ship.pos = pos;
+        }
+        ship.velocity = leg.velocity;
+        ship.thrust = None;
+        if i > 0 {
+            course.path.extend(leg.path);
+            course.closest_flyby = course.closest_flyby.min(leg.closest_flyby);
+        }
+        if let Some(mut impact) = leg.impact {
+            impact.time += ship.start_time;
+            course.impact = Some(impact);
+            break;
+        }
+        ship.start_time = leg_end;
+        if let Some(node) = nodes.get(i) {
+            let prograde = ship.velocity.normalize_or_zero();
+            let mut radial = prograde.perp();
+            if radial.dot(gravity_at(ship.pos, leg_end, bodies)) > 0.0 {
+                radial = -radial;
+            }
+            let delta_v = prograde * node.prograde + radial * node.radial;
+            ship.velocity += delta_v;
+            planned_nodes.push(PlannedNode {
+                node: *node,
+                pos: ship.pos,
+                delta_v,
+            });
+        }
+    }
+    course.velocity = ship.velocity;
+    PlannedManeuvers {
+        nodes: planned_nodes,
+        course,
+    }
+}
+
+pub fn setup(app: &mut App) {
+    app.add_systems(OnEnter(AppState::Game), reset_maneuvers);
+    app.add_systems(
+        Update,
+        read_maneuver_keys
+            .before(edit_nodes)
+            .run_if(in_state(AppState::Game)),
+    );
+}
+
+fn reset_maneuvers(mut commands: Commands) {
+    commands.insert_resource(ManeuverPlan::default());
+    commands.insert_resource(ManeuverKeys::default());
+}
+
+fn read_maneuver_keys(keys: Res<Input<KeyCode>>, mut pressed: ResMut<ManeuverKeys>) {
+    pressed.toggle_autopilot |= keys.just_pressed(KeyCode::X);
+    pressed.new_node |= keys.just_pressed(KeyCode::N);
+    pressed.select_next |= keys.just_pressed(KeyCode::Tab);
+    pressed.delete |= keys.just_pressed(KeyCode::Back);
+}
+
+// N: new node, Tab: select next node, Backspace: delete it,
+// Comma/Period: slide it along the path, I/K: prograde/retrograde, L/J: radial out/in,
+// X: toggle the autopilot executing the next node. Flying by hand disengages it.
+pub fn edit_nodes(
+    mut commands: Commands,
+    time: Res<Time>,
+    keys: Res<Input<KeyCode>>,
+    mut pressed: ResMut<ManeuverKeys>,
+    mut plan: ResMut<ManeuverPlan>,
+    player: Query<(Entity, Option<&Autopilot>), With<PlayerMarker>>,
+) {
+    let now = time.elapsed_seconds();
+    let dt = time.delta_seconds();
+    let pressed = std::mem::take(&mut *pressed);
+
+    if let Ok((entity, autopilot)) = player.get_single() {
+        let manual_flight = keys.any_pressed([
+            KeyCode::Up,
+            KeyCode::W,
+            KeyCode::Right,
+            KeyCode::D,
+            KeyCode::Left,
+            KeyCode::A,
+        ]);
+        if autopilot.is_some() && (manual_flight || pressed.toggle_autopilot) {
+            commands.entity(entity).remove::<Autopilot>();
+        } else if autopilot.is_none() && pressed.toggle_autopilot {
+            commands.entity(entity).insert(Autopilot::default());
+        }
+        if !autopilot.is_some_and(|autopilot| autopilot.burning()) {
+            plan.nodes.retain(|node| node.at > now);
+        }
+    }
+    if pressed.new_node {
+        let after = plan.nodes.get(plan.selected).map_or(now, |node| node.at);
+        plan.nodes.push(ManeuverNode {
+            at: after.max(now) + NODE_DEFAULT_LEAD_S,
+            prograde: 0.0,
+            radial: 0.0,
+        });
+        plan.selected = plan.nodes.len() - 1;
+    }
+    if pressed.select_next && !plan.nodes.is_empty() {
+        plan.selected = (plan.selected + 1) % plan.nodes.len();
+    }
+    if pressed.delete && plan.selected < plan.nodes.len() {
+        let selected = plan.selected;
+        plan.nodes.remove(selected);
+    }
+    plan.selected = plan.selected.min(plan.nodes.len().saturating_sub(1));
+
+    let selected = plan.selected;
+    if let Some(node) = plan.nodes.get_mut(selected) {
+        let axis = |positive: KeyCode, negative: KeyCode| {
+            (keys.pressed(positive) as i32 - keys.pressed(negative) as i32) as f32 * dt
+        };
+        node.at = (node.at + axis(KeyCode::Period, KeyCode::Comma) * NODE_TIME_RATE).max(now);
+        node.prograde += axis(KeyCode::I, KeyCode::K) * NODE_DELTA_V_RATE;
+        node.radial += axis(KeyCode::L, KeyCode::J) * NODE_DELTA_V_RATE;
+    }
+}
+
+// Aligns the ship with the next node's delta-v, and burns around the node's time until it's spent.
+pub fn execute_node(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut plan: ResMut<ManeuverPlan>,
+    mut impulses: EventWriter<AddExternalImpulse>,
+    mut player: Query<AutopilotedPlayer, With<PlayerMarker>>,
+) {
+    let Ok((entity, transform, velocity, traj, mut thruster, mut autopilot)) =
+        player.get_single_mut()
+    else {
+        return;
+    };
+    let autopilot = autopilot.as_mut();
+    let now = time.elapsed_seconds();
+    let dt = time.delta_seconds();
+    let thrust_to_acceleration = GLOBAL_IMPULSE_DURATION_MULT / PLAYER_MASS;
+
+    if autopilot.burn.is_none() {
+        let Some(next) = traj.maneuvers.as_ref().and_then(|m| m.nodes.first()) else {
+            // Nothing left to execute.
+            commands.entity(entity).remove::<Autopilot>();
+            return;
+        };
+        let burn_duration = next.delta_v.length() / (thruster.max_thrust * thrust_to_acceleration);
+        if now >= next.node.at - burn_duration / 2.0 {
+            autopilot.burn = Some(AutopilotBurn {
+                node: next.node,
+                direction: next.delta_v.normalize_or_zero(),
+                remaining_delta_v: next.delta_v.length(),
+            });
+        } else {
+            point_at(autopilot, transform, velocity, &time, next.delta_v);
+        }
+    }
+
+    let mut torque = 0.0;
+    if let Some(burn) = autopilot.burn.as_mut() {
+        let forward = transform.up().xy();
+        burn.remaining_delta_v -=
+            thruster.current_thrust * thrust_to_acceleration * dt * forward.dot(burn.direction);
+        let direction = burn.direction;
+        if burn.remaining_delta_v <= 0.0 {
+            let executed = burn.node;
+            thruster.release(dt);
+            plan.nodes.retain(|node| *node != executed);
+            commands.entity(entity).remove::<Autopilot>();
+            return;
+        }
+        point_at(autopilot, transform, velocity, &time, direction);
+        let orientation = forward.y.atan2(forward.x);
+        if autopilot
+            .orientation_controller
+            .at_target(orientation, AUTOPILOT_MAX_BURN_THETA)
+        {
+            thruster.throttle(dt);
+        } else {
+            thruster.release(dt);
+        }
+    } else {
+        thruster.release(dt);
+    }
+
+    let (cmd_torque, cmd_end_time) = autopilot.orientation_controller.current_command;
+    if now < cmd_end_time {
+        torque = cmd_torque;
+    }
+    impulses.send(AddExternalImpulse {
+        entity,
+        impulse: Vec2::ZERO,
+        torque_impulse: torque * dt * GLOBAL_IMPULSE_DURATION_MULT,
+    });
+}
+
+fn point_at(
+    autopilot: &mut Autopilot,
+    transform: &Transform,
+    velocity: &Velocity,
+    time: &Time,
+    direction: Vec2,
+) {
+    let forward = transform.up().xy();
+    let controller = &mut autopilot.orientation_controller;
+    controller.target(direction.y.atan2(direction.x));
+    if time.elapsed_seconds() >= controller.current_command.1 {
+        controller.update_command(time, forward.y.atan2(forward.x), velocity.angvel);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::{plan_maneuvers, ManeuverNode};
+    use crate::{celestial_body::CircularOrbitChain, gravity::PlannedShip};
+
+    fn ship(pos: Vec2, velocity: Vec2) -> PlannedShip {
+        PlannedShip {
+            start_time: 0.0,
+            pos,
+            velocity,
+            radius: 32.0,
+            thrust: None,
+        }
+    }
+
+    #[test]
+    fn nodes_change_the_course_in_empty_space() {
+        let nodes = [
+            ManeuverNode {
+                at: 101.0,
+                prograde: 100.0,
+                radial: 0.0,
+            },
+            ManeuverNode {
+                at: 102.5,
+                prograde: -50.0,
+                radial: 0.0,
+            },
+        ];
+        let planned = plan_maneuvers(
+            100.0,
+            0.05,
+            0.5,
+            ship(Vec2::ZERO, Vec2::new(100.0, 0.0)),
+            &nodes,
+            &[],
+        );
+        assert_eq!(planned.nodes.len(), 2);
+        assert!(planned.nodes[0].pos.distance(Vec2::new(100.0, 0.0)) < 0.1);
+        assert!(planned.nodes[0].delta_v.distance(Vec2::new(100.0, 0.0)) < 1e-3);
+        assert!(planned.nodes[1].pos.distance(Vec2::new(400.0, 0.0)) < 0.1);
+        assert!(planned.course.velocity.distance(Vec2::new(150.0, 0.0)) < 1e-3);
+        // 30 seconds after the last node.
+        let (end, _) = planned.course.path.last().unwrap();
+        assert!(end.distance(Vec2::new(4900.0, 0.0)) < 1.0, "{:?}", end);
+    }
+
+    #[test]
+    fn radial_points_away_from_gravity() {
+        let body = (
+            1e4,
+            100.0,
+            CircularOrbitChain {
+                origin: Vec2::ZERO,
+                chain: vec![],
+            },
+        );
+        let node = ManeuverNode {
+            at: 0.01,
+            prograde: 0.0,
+            radial: 50.0,
+        };
+        let planned = plan_maneuvers(
+            0.0,
+            0.05,
+            0.5,
+            ship(Vec2::new(1000.0, 0.0), Vec2::new(0.0, 100.0)),
+            &[node],
+            &[body],
+        );
+        let delta_v = planned.nodes[0].delta_v;
+        assert!(delta_v.x > 49.0 && delta_v.y.abs() < 1.0, "{:?}", delta_v);
+    }
+}
@@ -0,0 +1,490 @@
+use std::f32::consts::TAU;
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_rapier2d::{
+    dynamics::{Ccd, Damping, RigidBody, Velocity},
+    geometry::{Collider, ColliderMassProperties},
+};
+
+use crate::{
+    ai::{
+        maneuvers::Pilot,
+        orientation_controller::{OrientationController, ALIEN_SHIP_ANGULAR_INERTIA},
+        position_controller::PositionController,
+    },
+    alien_ship::{
+        AlienShipMarker, Weapon, ALIEN_SHIP_DRIVE_ENGINE_IMPULSE, ALIEN_SHIP_MASS,
+        ALIEN_SHIP_ROTATION_IMPULSE,
+    },
+    camera::game_layer,
+    celestial_body::{CelestialBodyMarker, CircularOrbitChain},
+    collisions_handler,
+    course_planner::ComputedTrajectory,
+    death::KillCredit,
+    despawn_queue::DespawnQueue,
+    gameplay_events::HardpointDestroyed,
+    gravity::{gravity_formula, AffectedByGravity},
+    healthpoints::HealthPoints,
+    lasers::{self, Laser, LaserAbility, LaserOrigin, LASER_LIFETIME_S},
+    player::{PlayerMarker, PLAYER_LASER_DAMAGE},
+    simulation::SimulationMode,
+    system_sets::AppStage,
+    thruster::Thruster,
+    ui::GameSettings,
+    AppState,
+};
+
+// Every this many waves, a mothership joins the wave.
+pub const BOSS_WAVE_EVERY: u32 = 5;
+
+const BOSS_HULL_RADIUS: f32 = 160.0;
+const BOSS_HULL_HP: f32 = 100.0 * PLAYER_LASER_DAMAGE;
+const BOSS_MASS: f32 = 8.0;
+const BOSS_TINT: Color = Color::rgb(1.0, 0.6, 0.6);
+const BOSS_SHIELDED_TINT: Color = Color::rgb(0.6, 0.8, 1.0);
+
+const HARDPOINT_RADIUS: f32 = 24.0;
+const HARDPOINT_MASS: f32 = 0.25;
+// Hardpoints sit on the hull's rim, clockwise from the nose. Engines are at the back.
+const BOSS_LAYOUT: [HardpointKind; 8] = [
+    HardpointKind::Turret,
+    HardpointKind::Turret,
+    HardpointKind::ShieldGenerator,
+    HardpointKind::Engine,
+    HardpointKind::Turret,
+    HardpointKind::Engine,
+    HardpointKind::ShieldGenerator,
+    HardpointKind::Turret,
+];
+const BOSS_ENGINES: usize = 2;
+
+// The hull and its hardpoints make up a single rigid body.
+const BOSS_TOTAL_MASS: f32 = BOSS_MASS + BOSS_LAYOUT.len() as f32 * HARDPOINT_MASS;
+const BOSS_ANGULAR_INERTIA: f32 = (0.5 * BOSS_MASS + BOSS_LAYOUT.len() as f32 * HARDPOINT_MASS)
+    * BOSS_HULL_RADIUS
+    * BOSS_HULL_RADIUS;
+// Accelerations, relative to a raider's.
+const BOSS_ENGINE: f32 = 0.4;
+const BOSS_ROTATION: f32 = 0.25;
+const BOSS_THRUST: f32 =
+    ALIEN_SHIP_DRIVE_ENGINE_IMPULSE * BOSS_ENGINE * BOSS_TOTAL_MASS / ALIEN_SHIP_MASS;
+const BOSS_TORQUE: f32 =
+    ALIEN_SHIP_ROTATION_IMPULSE * BOSS_ROTATION * BOSS_ANGULAR_INERTIA / ALIEN_SHIP_ANGULAR_INERTIA;
+
+// Never circle a body closer than this, whatever the distance we showed up at.
+const BOSS_MIN_ORBIT_RADIUS: f32 = 3000.0;
+// Once its shields are down, the mothership circles the player at this distance.
+const BOSS_HUNT_RADIUS: f32 = 1500.0;
+const BOSS_HUNT_SPEED: f32 = 300.0;
+
+const TURRET_WEAPON: Weapon = Weapon::Laser {
+    damage: 15.0,
+    speed: 1500.0,
+    lifetime: LASER_LIFETIME_S,
+    range: 3000.0,
+};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HardpointKind {
+    Turret,
+    ShieldGenerator, // while any is up, the rest of the mothership can't be hurt
+    Engine,
+}
+
+// A separately destructible part of a mothership, a child collider of its hull.
+#[derive(Component)]
+pub struct Hardpoint {
+    pub kind: HardpointKind,
+}
+
+// The mothership's script, as its hardpoints go down.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BossPhase {
+    Shielded, // circles a body, turrets fire at leisure
+    Exposed,  // hunts the player down, turrets fire faster
+    Crippled, // drifts along, firing everything it has left
+}
+
+impl BossPhase {
+    pub fn of(shield_generators: usize, engines: usize) -> BossPhase {
+        if engines == 0 {
+            BossPhase::Crippled
+        } else if shield_generators == 0 {
+            BossPhase::Exposed
+        } else {
+            BossPhase::Shielded
+        }
+    }
+
+    fn turret_cooldown(&self) -> f32 {
+        match self {
+            BossPhase::Shielded => 1.5,
+            BossPhase::Exposed => 0.75,
+            BossPhase::Crippled => 0.4,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct Mothership {
+    pub phase: BossPhase,
+    shield_generators: usize,     // still standing
+    orbit: Option<(Entity, f32)>, // the body we circle, and at which distance
+}
+
+impl Mothership {
+    // Whatever the phase: a crippled mothership stays shielded until its last generator falls.
+    pub fn shields_up(&self) -> bool {
+        self.shield_generators > 0
+    }
+}
+
+type Motherships<'a> = (
+    Entity,
+    &'a mut Mothership,
+    &'a mut Thruster,
+    &'a mut PositionController,
+    &'a mut Sprite,
+);
+
+type FlyingMothership<'a> = (
+    &'a Transform,
+    &'a Velocity,
+    &'a mut Mothership,
+    &'a mut OrientationController,
+    &'a mut PositionController,
+);
+
+type OrbitedBodies<'w, 's> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'static Transform,
+        &'static ColliderMassProperties,
+        &'static CircularOrbitChain,
+    ),
+    With<CelestialBodyMarker>,
+>;
+
+pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
+    app.add_systems(
+        schedule,
+        (update_hardpoints, fly_motherships, fire_turrets)
+            .chain()
+            .in_set(AppStage::Control)
+            .run_if(in_state(AppState::Game)),
+    );
+}
+
+pub fn spawn(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    pos: Vec2,
+    velocity: Vec2,
+) -> Entity {
+    let texture = asset_server.load("enemy_ship.png");
+    let mut cmd = commands.spawn((
+        AlienShipMarker,
+        Mothership {
+            phase: BossPhase::Shielded,
+            shield_generators: BOSS_LAYOUT
+                .iter()
+                .filter(|&&kind| kind == HardpointKind::ShieldGenerator)
+                .count(),
+            orbit: None,
+        },
+        HealthPoints::new(BOSS_HULL_HP),
+        KillCredit::default(),
+        Thruster {
+            max_thrust: BOSS_THRUST,
+            current_thrust: 0.0,
+            rampup_rate: BOSS_THRUST,
+            shutoff_rate: BOSS_THRUST,
+            ignition_thrust: BOSS_THRUST / 2.0,
+        },
+        OrientationController::new(BOSS_TORQUE * 0.9).with_angular_inertia(BOSS_ANGULAR_INERTIA),
+        // The controller assumes a raider's mass.
+        PositionController::new(BOSS_THRUST * 0.75 * ALIEN_SHIP_MASS / BOSS_TOTAL_MASS),
+        // The hull has no weapon of its own, its turrets do the shooting.
+        LaserAbility {
+            last_shot: None,
+            cooldown: 0.0,
+        },
+        ComputedTrajectory::default(),
+        SpriteBundle {
+            texture: texture.clone(),
+            sprite: Sprite {
+                color: BOSS_SHIELDED_TINT,
+                custom_size: Some(Vec2::splat(2.0 * BOSS_HULL_RADIUS)),
+                ..default()
+            },
+            transform: Transform::from_translation(pos.extend(0.0)),
+            ..default()
+        },
+        collisions_handler::ship_contact_events(),
+        AffectedByGravity::default(),
+        game_layer(),
+    ));
+    cmd.insert((
+        Ccd::enabled(),
+        RigidBody::Dynamic,
+        Collider::ball(BOSS_HULL_RADIUS),
+        ColliderMassProperties::Mass(BOSS_MASS),
+        Damping {
+            linear_damping: 0.0,
+            angular_damping: 0.5,
+        },
+        Velocity {
+            linvel: velocity,
+            ..default()
+        },
+    ));
+    cmd.with_children(|hull| {
+        for (i, &kind) in BOSS_LAYOUT.iter().enumerate() {
+            let angle = -(i as f32) * TAU / BOSS_LAYOUT.len() as f32;
+            // In shots from the player.
+            let (shots, color) = match kind {
+                HardpointKind::Turret => (10.0, Color::rgb(1.0, 0.35, 0.3)),
+                HardpointKind::ShieldGenerator => (15.0, Color::rgb(0.5, 0.8, 1.0)),
+                HardpointKind::Engine => (15.0, Color::rgb(1.0, 0.7, 0.2)),
+            };
+            hull.spawn((
+                Hardpoint { kind },
+                HealthPoints::new(shots * PLAYER_LASER_DAMAGE),
+                LaserAbility {
+                    last_shot: None,
+                    cooldown: BossPhase::Shielded.turret_cooldown(),
+                },
+                SpriteBundle {
+                    texture: texture.clone(),
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::splat(2.0 * HARDPOINT_RADIUS)),
+                        ..default()
+                    },
+                    // Facing outwards, at the rim.
+                    transform: Transform::from_translation(
+                        (Vec2::from_angle(angle).rotate(Vec2::Y) * BOSS_HULL_RADIUS).extend(0.1),
+                    )
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                    ..default()
+                },
+                Collider::ball(HARDPOINT_RADIUS),
+                ColliderMassProperties::Mass(HARDPOINT_MASS),
+                collisions_handler::ship_contact_events(),
+                game_layer(),
+            ));
+        }
+    });
+    cmd.id()
+}
+
+// Clears destroyed hardpoints, and moves the script along with what's left.
+pub fn update_hardpoints(
+    mut despawn_queue: ResMut<DespawnQueue>,
+    hardpoints: Query<(Entity, &Parent, &Hardpoint, &HealthPoints, &GlobalTransform)>,
+    velocities: Query<&Velocity>,
+    mut motherships: Query<Motherships>,
+    mut destroyed: EventWriter<HardpointDestroyed>,
+) {
+    let mut remaining: HashMap<Entity, (usize, usize)> = HashMap::new();
+    for (entity, parent, hardpoint, hp, gt) in hardpoints.iter() {
+        let mothership = parent.get();
+        if hp.current <= 0.0 {
+            if despawn_queue.1.insert(entity) {
+                destroyed.send(HardpointDestroyed {
+                    mothership,
+                    kind: hardpoint.kind,
+                    position: gt.translation().xy(),
+                    velocity: velocities.get(mothership).map_or(Vec2::ZERO, |v| v.linvel),
+                });
+            }
+            continue;
+        }
+        let (shield_generators, engines) = remaining.entry(mothership).or_default();
+        match hardpoint.kind {
+            HardpointKind::ShieldGenerator => *shield_generators += 1,
+            HardpointKind::Engine => *engines += 1,
+            HardpointKind::Turret => {}
+        }
+    }
+    for (entity, mut mothership, mut thruster, mut position_controller, mut sprite) in
+        motherships.iter_mut()
+    {
+        let (shield_generators, engines) = remaining.get(&entity).copied().unwrap_or_default();
+        let phase = BossPhase::of(shield_generators, engines);
+        if phase != mothership.phase {
+            debug!("Mothership {:?} is now {:?}", entity, phase);
+            mothership.phase = phase;
+        }
+        mothership.shield_generators = shield_generators;
+        // Each engine lost takes its share of the thrust with it.
+        let thrust = BOSS_THRUST * engines as f32 / BOSS_ENGINES as f32;
+        thruster.max_thrust = thrust;
+        thruster.ignition_thrust = thrust / 2.0;
+        position_controller.thrust_available = thrust * 0.75 * ALIEN_SHIP_MASS / BOSS_TOTAL_MASS;
+        sprite.color = if mothership.shields_up() {
+            BOSS_SHIELDED_TINT
+        } else {
+            BOSS_TINT
+        };
+    }
+}
+
+pub fn fly_motherships(
+    time: Res<Time>,
+    player: Query<(&Transform, &Velocity), With<PlayerMarker>>,
+    bodies: OrbitedBodies,
+    mut motherships: Query<FlyingMothership>,
+) {
+    let Ok((pt, pv)) = player.get_single() else {
+        return;
+    };
+    for (t, v, mut mothership, mut orientation_controller, mut position_controller) in
+        motherships.iter_mut()
+    {
+        // Let the current maneuver run its course.
+        if time.elapsed_seconds() < position_controller.current_command.1 {
+            continue;
+        }
+        let pos = t.translation.xy();
+        let forward = t.up().xy();
+        let mut pilot = Pilot {
+            time: &time,
+            orientation: forward.y.atan2(forward.x),
+            angular_velocity: v.angvel,
+            orientation_controller: &mut orientation_controller,
+            position_controller: &mut position_controller,
+        };
+        match mothership.phase {
+            BossPhase::Shielded => {
+                // We settle around whichever body is nearest when we show up.
+                if mothership.orbit.is_none() {
+                    mothership.orbit = bodies
+                        .iter()
+                        .map(|(body, bt, ..)| (body, bt.translation.xy().distance(pos)))
+                        .min_by(|a, b| a.1.total_cmp(&b.1))
+                        .map(|(body, distance)| (body, distance.max(BOSS_MIN_ORBIT_RADIUS)));
+                }
+                let Some((body, radius)) = mothership.orbit else {
+                    pilot.coast(1.0);
+                    continue;
+                };
+                let Ok((_, bt, &ColliderMassProperties::Mass(mass), orbit)) = bodies.get(body)
+                else {
+                    mothership.orbit = None;
+                    continue;
+                };
+                let speed = (gravity_formula(radius, mass) * radius).sqrt();
+                pilot.orbit(
+                    bt.translation.xy() - pos,
+                    orbit.velocity() - v.linvel,
+                    radius,
+                    speed,
+                );
+            }
+            BossPhase::Exposed => pilot.orbit(
+                pt.translation.xy() - pos,
+                pv.linvel - v.linvel,
+                BOSS_HUNT_RADIUS,
+                BOSS_HUNT_SPEED,
+            ),
+            BossPhase::Crippled => pilot.coast(1.0),
+        }
+    }
+}
+
+pub fn fire_turrets(
+    mut commands: Commands,
+    time: Res<Time>,
+    settings: Res<GameSettings>,
+    player: Query<(&Transform, &Velocity, &AffectedByGravity), With<PlayerMarker>>,
+    motherships: Query<(&Velocity, &AffectedByGravity, &Mothership)>,
+    mut turrets: Query<(&Parent, &Hardpoint, &GlobalTransform, &mut LaserAbility)>,
+) {
+    let Ok((pt, pv, player_gravity)) = player.get_single() else {
+        return;
+    };
+    let Weapon::Laser {
+        damage,
+        speed,
+        lifetime,
+        range,
+    } = TURRET_WEAPON
+    else {
+        return;
+    };
+    for (parent, hardpoint, gt, mut turret) in turrets.iter_mut() {
+        let Ok((v, gravity, mothership)) = motherships.get(parent.get()) else {
+            continue;
+        };
+        if hardpoint.kind != HardpointKind::Turret {
+            continue;
+        }
+        turret.cooldown = mothership.phase.turret_cooldown();
+        let pos = gt.translation().xy();
+        let d = pt.translation.xy() - pos;
+        if !turret.ready(&time) || d.length() > range {
+            continue;
+        }
+        let aim = TURRET_WEAPON
+            .aim(
+                d,
+                pv.linvel - v.linvel,
+                player_gravity.last_acceleration - gravity.last_acceleration,
+                settings.difficulty.aim_accuracy(),
+            )
+            .normalize_or_zero();
+        // Turrets only cover their side of the hull.
+        if gt.up().xy().dot(aim) <= 0.0 {
+            continue;
+        }
+        lasers::spawn(
+            &mut commands,
+            pos + aim * (HARDPOINT_RADIUS + 10.0),
+            aim * speed + v.linvel,
+            Laser {
+                origin: LaserOrigin::Enemy,
+                shooter: parent.get(),
+                damage,
+                shot_at: time.elapsed_seconds(),
+                lifetime,
+            },
+        );
+        turret.last_shot = Some(time.elapsed_seconds());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BossPhase, HardpointKind, Mothership, BOSS_ENGINES, BOSS_LAYOUT};
+
+    #[test]
+    fn bosses_go_through_their_phases_as_hardpoints_fall() {
+        let count = |kind| BOSS_LAYOUT.iter().filter(|&&k| k == kind).count();
+        let shield_generators = count(HardpointKind::ShieldGenerator);
+        assert_eq!(count(HardpointKind::Engine), BOSS_ENGINES);
+        assert_eq!(
+            BossPhase::of(shield_generators, BOSS_ENGINES),
+            BossPhase::Shielded
+        );
+        assert_eq!(BossPhase::of(1, 1), BossPhase::Shielded);
+        assert_eq!(BossPhase::of(0, BOSS_ENGINES), BossPhase::Exposed);
+        // Losing every engine cripples it, shielded or not.
+        assert_eq!(BossPhase::of(0, 0), BossPhase::Crippled);
+        assert_eq!(BossPhase::of(shield_generators, 0), BossPhase::Crippled);
+        // But only the generators bring the shields down.
+        let crippled = |shield_generators| Mothership {
+            phase: BossPhase::Crippled,
+            shield_generators,
+            orbit: None,
+        };
+        assert!(crippled(1).shields_up());
+        assert!(!crippled(0).shields_up());
+        // The further along the script, the faster the turrets.
+        assert!(BossPhase::Exposed.turret_cooldown() < BossPhase::Shielded.turret_cooldown());
+        assert!(BossPhase::Crippled.turret_cooldown() < BossPhase::Exposed.turret_cooldown());
+    }
+}
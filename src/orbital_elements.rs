@@ -0,0 +1,261 @@
+use std::f32::consts::PI;
+
+use bevy::{
+    prelude::*,
+    tasks::{block_on, AsyncComputeTaskPool, Task},
+};
+use bevy_rapier2d::{
+    dynamics::Velocity,
+    geometry::{Collider, ColliderMassProperties},
+};
+
+use crate::{
+    celestial_body::{CelestialBodyMarker, CircularOrbitChain},
+    course_planner::scaled_radius,
+    gravity::{gravity_formula, gravity_potential, plan_course, PlannedShip},
+    player::PlayerMarker,
+    AppState,
+};
+
+const REFRESH_PERIOD_S: f32 = 0.25;
+const STEPS_PER_ORBIT: f32 = 720.0;
+const MAX_STEPS: f32 = 20000.0;
+const MAX_ORBIT_DURATION_S: f32 = 600.0;
+const PLAN_TOLERANCE: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug)]
+pub struct OrbitalElements {
+    // Distances from the body's center.
+    pub periapsis: f32,
+    pub apoapsis: Option<f32>, // None when escaping, or when the orbit doesn't close in time
+    pub period: Option<f32>,   // time for a full revolution
+    pub escaping: bool,
+    pub crashing: bool,
+}
+
+#[derive(Resource, Default)]
+pub struct PlayerOrbit {
+    pub body: Option<Entity>,
+    pub body_radius: f32,
+    pub elements: Option<OrbitalElements>,
+    updated_at: f32,
+    // Integrating a whole revolution takes thousands of steps, it runs off the main thread.
+    task: Option<Task<OrbitalElements>>,
+}
+
+pub fn setup(app: &mut App) {
+    app.init_resource::<PlayerOrbit>();
+    app.add_systems(OnEnter(AppState::Game), reset_player_orbit);
+    app.add_systems(Update, update.run_if(in_state(AppState::Game)));
+}
+
+fn reset_player_orbit(mut commands: Commands) {
+    commands.insert_resource(PlayerOrbit::default());
+}
+
+// The body pulling the hardest on `pos`.
+pub fn dominant_body<'a, T>(
+    pos: Vec2,
+    bodies: impl Iterator<Item = (T, f32, &'a CircularOrbitChain)>, // (id, mass, orbit)
+) -> Option<T> {
+    bodies
+        .map(|(id, mass, orbit)| (id, gravity_formula(orbit.pos(0.0).distance(pos), mass)))
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map(|(id, _)| id)
+}
+
+// Our d^1.6 law doesn't produce Keplerian ellipses: orbits precess, and the textbook formulas don't apply.
+// Instead, we integrate the two-body motion for a full revolution around the body and measure it.
+pub fn orbital_elements(
+    pos: Vec2, // relative to the body
+    velocity: Vec2,
+    mass: f32,
+    radius: f32,
+    ship_radius: f32,
+) -> OrbitalElements {
+    let r = pos.length();
+    let escaping = velocity.length_squared() / 2.0 + gravity_potential(r, mass) >= 0.0;
+
+    // Size the steps after the period of a circular orbit at the current distance and speed.
+    let step_dt = (2.0 * PI * r / velocity.length().max(1.0) / STEPS_PER_ORBIT).clamp(0.005, 1.0);
+    let max_dt = MAX_ORBIT_DURATION_S.min(step_dt * MAX_STEPS);
+    let body = (
+        mass,
+        radius,
+        CircularOrbitChain {
+            origin: Vec2::ZERO,
+            chain: vec![],
+        },
+    );
+    let plan = plan_course(
+        max_dt,
+        step_dt,
+        PLAN_TOLERANCE,
+        &PlannedShip {
+            start_time: 0.0,
+            pos,
+            velocity,
+            radius: ship_radius,
+            thrust: None,
+        },
+        &[body],
+    );
+
+    let (mut periapsis, mut apoapsis) = (r, r);
+    let mut period = None;
+    let mut swept = 0.0f32;
+    let mut previous = pos;
+    for (i, &(point, _)) in plan.path.iter().enumerate() {
+        let angle = previous.angle_between(point);
+        if (swept + angle).abs() >= 2.0 * PI {
+            // Interpolate the moment we complete the revolution.
+            let fraction = (2.0 * PI - swept.abs()) / angle.abs();
+            period = Some((i as f32 + fraction) * step_dt);
+            break;
+        }
+        swept += angle;
+        periapsis = periapsis.min(point.length());
+        apoapsis = apoapsis.max(point.length());
+        previous = point;
+    }
+
+    OrbitalElements {
+        periapsis,
+        apoapsis: period.and(Some(apoapsis)).filter(|_| !escaping),
+        period: period.filter(|_| !escaping),
+        escaping,
+        crashing: plan.impact.is_some(),
+    }
+}
+
+fn update(
+    time: Res<Time>,
+    mut orbit: ResMut<PlayerOrbit>,
+    player: Query<(&Transform, &Velocity, &Collider), With<PlayerMarker>>,
+    bodies: Query<
+        (
+            Entity,
+            &Transform,
+            &ColliderMassProperties,
+            &Collider,
+            &CircularOrbitChain,
+        ),
+        With<CelestialBodyMarker>,
+    >,
+) {
+    // Only a HUD reads these: we don't hold the frame for them, even in deterministic mode.
+    if let Some(task) = orbit.task.as_mut() {
+        if !task.is_finished() {
+            return;
+        }
+        let elements = block_on(task);
+        orbit.elements = Some(elements);
+        orbit.task = None;
+    }
+    if time.elapsed_seconds() - orbit.updated_at < REFRESH_PERIOD_S {
+        return;
+    }
+    orbit.updated_at = time.elapsed_seconds();
+    let Ok((pt, pv, player_collider)) = player.get_single() else {
+        return;
+    };
+    let pos = pt.translation.xy();
+    let dominant = dominant_body(
+        pos,
+        bodies
+            .iter()
+            .filter_map(|(entity, _, mass_props, _, chain)| match mass_props {
+                &ColliderMassProperties::Mass(m) => Some((entity, m, chain)),
+                _ => None,
+            }),
+    );
+    if dominant != orbit.body {
+        orbit.body = dominant;
+        orbit.elements = None;
+    }
+    let Some(Ok((_, t, &ColliderMassProperties::Mass(mass), collider, chain))) =
+        dominant.map(|entity| bodies.get(entity))
+    else {
+        orbit.elements = None;
+        return;
+    };
+    orbit.body_radius = scaled_radius(collider, t);
+    let (pos, velocity, body_radius, ship_radius) = (
+        pos - chain.pos(0.0),
+        pv.linvel - chain.velocity(),
+        orbit.body_radius,
+        scaled_radius(player_collider, pt),
+    );
+    orbit.task = Some(
+        AsyncComputeTaskPool::get()
+            .spawn(async move { orbital_elements(pos, velocity, mass, body_radius, ship_radius) }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::orbital_elements;
+    use crate::gravity::{gravity_formula, gravity_potential};
+
+    const MASS: f32 = 5e7;
+
+    #[test]
+    fn circular_orbit() {
+        let r = 3000.0;
+        let v = (gravity_formula(r, MASS) * r).sqrt();
+        let elements = orbital_elements(Vec2::new(r, 0.0), Vec2::new(0.0, v), MASS, 500.0, 32.0);
+        assert!(!elements.escaping && !elements.crashing);
+        assert!((elements.periapsis - r).abs() < r * 0.01);
+        assert!((elements.apoapsis.unwrap() - r).abs() < r * 0.01);
+        let period = elements.period.unwrap();
+        let expected = 2.0 * std::f32::consts::PI * r / v;
+        assert!(
+            (period - expected).abs() < expected * 0.01,
+            "{} != {}",
+            period,
+            expected
+        );
+    }
+
+    #[test]
+    fn eccentric_orbit() {
+        // Slower than circular at apoapsis: we fall towards a lower periapsis.
+        let r = 3000.0;
+        let v = (gravity_formula(r, MASS) * r).sqrt() * 0.8;
+        let elements = orbital_elements(Vec2::new(r, 0.0), Vec2::new(0.0, v), MASS, 500.0, 32.0);
+        assert!(!elements.escaping && !elements.crashing);
+        assert!(elements.periapsis < r * 0.9, "{:?}", elements);
+        assert!((elements.apoapsis.unwrap() - r).abs() < r * 0.01);
+        // Both apsides conserve energy.
+        let energy_at =
+            |d: f32, velocity: f32| velocity * velocity / 2.0 + gravity_potential(d, MASS);
+        let angular_momentum = r * v;
+        let periapsis_speed = angular_momentum / elements.periapsis;
+        let (e0, e1) = (
+            energy_at(r, v),
+            energy_at(elements.periapsis, periapsis_speed),
+        );
+        assert!((e0 - e1).abs() < e0.abs() * 0.01, "{} != {}", e0, e1);
+    }
+
+    #[test]
+    fn escape_and_crash() {
+        let r = 3000.0;
+        let escape_speed = (-2.0 * gravity_potential(r, MASS)).sqrt();
+        let elements = orbital_elements(
+            Vec2::new(r, 0.0),
+            Vec2::new(0.0, escape_speed * 1.01),
+            MASS,
+            500.0,
+            32.0,
+        );
+        assert!(elements.escaping);
+        assert!(elements.apoapsis.is_none() && elements.period.is_none());
+
+        let elements =
+            orbital_elements(Vec2::new(r, 0.0), Vec2::new(-100.0, 0.0), MASS, 500.0, 32.0);
+        assert!(elements.crashing && !elements.escaping);
+    }
+}
@@ -1,3 +1,4 @@
+pub mod sparks;
 pub mod thrusters;
 
 use bevy::{prelude::*, render::view::RenderLayers};
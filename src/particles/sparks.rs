@@ -0,0 +1,107 @@
+use std::f32::consts::PI;
+
+use bevy::prelude::*;
+use bevy_rapier2d::dynamics::Velocity;
+use colorgrad::CustomGradient;
+use rand::{distributions::Uniform, Rng};
+
+use crate::{
+    camera::game_layer,
+    gameplay_events::{BodyImpact, HardpointDestroyed, ShipDestroyed},
+    simulation::SimulationRng,
+    ui::{EntitiesQuantity, GameSettings},
+};
+
+use super::{Particle, ParticleKind};
+
+fn spawn_burst(
+    commands: &mut Commands,
+    rng: &mut impl Rng,
+    entities_quantity: EntitiesQuantity,
+    time: &Time,
+    origin: Vec2,
+    vel: Vec2,
+    strength: f32, // 1 for an exploding ship
+) {
+    let particle_angle_distribution = Uniform::new(0.0, PI * 2.0);
+    let particle_speed_distribution = Uniform::new(50.0, 800.0 * strength);
+    let n_mul = match entities_quantity {
+        EntitiesQuantity::Some => 20.0,
+        EntitiesQuantity::ALot => 40.0,
+        EntitiesQuantity::TooMuch => 60.0,
+    };
+    for _ in 0..(n_mul * strength) as u32 {
+        let theta = rng.sample(particle_angle_distribution);
+        let speed = rng.sample(particle_speed_distribution);
+        commands.spawn((
+            TransformBundle::from_transform(Transform::from_translation(origin.extend(-1.0))),
+            Particle {
+                lifetime: rng.gen::<f32>().abs().min(1.0).powf(2.0) * 0.8 + 0.2,
+                spawned_at: time.elapsed_seconds(),
+                kind: ParticleKind::Combustion {
+                    init_radius: rng.gen::<f32>().abs().min(1.0) * 8.0 * strength + 2.0,
+                    end_radius: 1.0,
+                    color: CustomGradient::new()
+                        .colors(&[
+                            colorgrad::Color::new(1.0, 1.0, 0.8, 1.0),
+                            colorgrad::Color::new(1.0, 0.5, 0.1, 0.8),
+                            colorgrad::Color::new(0.4, 0.1, 0.1, 0.0),
+                        ])
+                        .interpolation(colorgrad::Interpolation::Basis)
+                        .build()
+                        .unwrap(),
+                },
+            },
+            Velocity {
+                linvel: vel + Vec2::from_angle(theta) * speed,
+                angvel: 0.0,
+            },
+            game_layer(),
+        ));
+    }
+}
+
+pub fn spawn_sparks(
+    game_settings: Res<GameSettings>,
+    mut commands: Commands,
+    mut rng: ResMut<SimulationRng>,
+    time: Res<Time>,
+    mut destroyed: EventReader<ShipDestroyed>,
+    mut impacts: EventReader<BodyImpact>,
+    mut hardpoints: EventReader<HardpointDestroyed>,
+) {
+    let rng = &mut rng.particles;
+    for event in destroyed.read() {
+        spawn_burst(
+            &mut commands,
+            rng,
+            game_settings.entities_quantity,
+            &time,
+            event.position,
+            event.velocity,
+            1.0,
+        );
+    }
+    for event in impacts.read() {
+        spawn_burst(
+            &mut commands,
+            rng,
+            game_settings.entities_quantity,
+            &time,
+            event.position,
+            event.velocity,
+            0.3,
+        );
+    }
+    for event in hardpoints.read() {
+        spawn_burst(
+            &mut commands,
+            rng,
+            game_settings.entities_quantity,
+            &time,
+            event.position,
+            event.velocity,
+            0.6,
+        );
+    }
+}
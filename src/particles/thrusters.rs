@@ -7,6 +7,7 @@ use rand::{distributions::Uniform, Rng};
 
 use crate::{
     camera::{game_layer, GameCameraMarker},
+    simulation::SimulationRng,
     thruster::Thruster,
     ui::{EntitiesQuantity, GameSettings},
 };
@@ -15,13 +16,13 @@ use super::{Particle, ParticleKind};
 
 pub fn spawn_rotation_thruster_cone(
     commands: &mut Commands,
+    rng: &mut impl Rng,
     entities_quantity: EntitiesQuantity,
     time: &Time,
     origin: Vec2,
     vel: Vec2,
     direction: Vec2,
 ) {
-    let mut rng = rand::thread_rng();
     let cone_arc = PI / 11.0;
     let particle_angle_distribution = Uniform::new(-cone_arc / 2.0, cone_arc / 2.0);
     let particle_speed_distribution = Uniform::new(100.0, 600.0);
@@ -74,6 +75,7 @@ pub fn spawn_rotation_thruster_cone(
 pub fn spawn_main_thruster_particles(
     game_settings: Res<GameSettings>,
     mut commands: Commands,
+    mut rng: ResMut<SimulationRng>,
     time: Res<Time>,
     ships: Query<(&Transform, &Velocity, &Thruster)>,
     camera: Query<(&Transform, &OrthographicProjection), With<GameCameraMarker>>,
@@ -86,7 +88,7 @@ pub fn spawn_main_thruster_particles(
             max: cam_area.max + cam_pos,
         };
 
-        let mut rng = rand::thread_rng();
+        let rng = &mut rng.particles;
         let max_angle_at_lowest_thrust = PI / 3.0;
         let max_particle_speed = 1000.0;
         for (transform, velocity, thruster) in ships.iter() {
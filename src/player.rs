@@ -3,18 +3,21 @@ use std::f32::consts::PI;
 use bevy::prelude::*;
 use bevy_rapier2d::{
     dynamics::{Ccd, Damping, RigidBody, Velocity},
-    geometry::{ActiveEvents, Collider, ColliderMassProperties},
+    geometry::{Collider, ColliderMassProperties},
 };
 
 use crate::{
     camera::game_layer,
     celestial_body::{CircularOrbitChain, StarterPlanetMarker},
+    collisions_handler,
     course_planner::ComputedTrajectory,
     gravity::AffectedByGravity,
     healthpoints::HealthPoints,
     impulses_aggregator::AddExternalImpulse,
-    lasers::{self, Laser, LaserAbility, LaserOrigin},
+    lasers::{self, Laser, LaserAbility, LaserOrigin, LASER_LIFETIME_S},
+    maneuver::Autopilot,
     particles::thrusters::spawn_rotation_thruster_cone,
+    simulation::SimulationRng,
     thruster::Thruster,
     ui::{Difficulty, GameSettings},
     GLOBAL_IMPULSE_DURATION_MULT,
@@ -23,48 +26,55 @@ use crate::{
 pub const PLAYER_MASS: f32 = 4.0;
 const DRIVE_ENGINE_MAX_IMPULSE: f32 = 8.0 * PLAYER_MASS;
 const DRIVE_ENGINE_INIT_IMPULSE: f32 = 3.0 * PLAYER_MASS;
-const ROTATION_IMPULSE: f32 = 14.0 * DRIVE_ENGINE_MAX_IMPULSE;
+pub const ROTATION_IMPULSE: f32 = 14.0 * DRIVE_ENGINE_MAX_IMPULSE;
 
 const LASER_COOLDOWN_S: f32 = 0.02;
+// One shot downs a raider, tougher ships take several.
+pub const PLAYER_LASER_DAMAGE: f32 = 50.0;
 
 const STARTING_HP: f32 = 100.0;
 
 #[derive(Component)]
 pub struct PlayerMarker;
 
+type ControlledPlayer<'a> = (
+    Entity,
+    &'a mut LaserAbility,
+    &'a mut Thruster,
+    &'a Transform,
+    &'a Velocity,
+    Has<Autopilot>,
+);
+
 pub fn control(
     mut commands: Commands,
+    mut rng: ResMut<SimulationRng>,
     settings: Res<GameSettings>,
     time: Res<Time>,
     mut impulses: EventWriter<AddExternalImpulse>,
-    mut player: Query<
-        (
-            Entity,
-            &mut LaserAbility,
-            &mut Thruster,
-            &Transform,
-            &Velocity,
-        ),
-        With<PlayerMarker>,
-    >,
+    mut player: Query<ControlledPlayer, With<PlayerMarker>>,
     keys: Res<Input<KeyCode>>,
 ) {
-    if let Ok((entity, mut laser_ability, mut thruster, transform, velocity)) =
+    if let Ok((entity, mut laser_ability, mut thruster, transform, velocity, autopiloted)) =
         player.get_single_mut()
     {
         let mut angular_impulse = 0.0;
         let xy = transform.translation.xy();
         let particle_distance = 24.0;
-        if keys.pressed(KeyCode::Up) || keys.pressed(KeyCode::W) {
-            thruster.throttle(time.delta_seconds());
-        } else {
-            thruster.release(time.delta_seconds());
+        // The maneuver autopilot drives the thruster while it's engaged.
+        if !autopiloted {
+            if keys.pressed(KeyCode::Up) || keys.pressed(KeyCode::W) {
+                thruster.throttle(time.delta_seconds());
+            } else {
+                thruster.release(time.delta_seconds());
+            }
         }
 
         if keys.pressed(KeyCode::Right) || keys.pressed(KeyCode::D) {
             angular_impulse -= ROTATION_IMPULSE;
             spawn_rotation_thruster_cone(
                 &mut commands,
+                &mut rng.particles,
                 settings.entities_quantity,
                 &time,
                 xy + transform.right().xy().normalize() * particle_distance,
@@ -73,6 +83,7 @@ pub fn control(
             );
             spawn_rotation_thruster_cone(
                 &mut commands,
+                &mut rng.particles,
                 settings.entities_quantity,
                 &time,
                 xy + transform.left().xy().normalize() * particle_distance,
@@ -83,6 +94,7 @@ pub fn control(
             angular_impulse += ROTATION_IMPULSE;
             spawn_rotation_thruster_cone(
                 &mut commands,
+                &mut rng.particles,
                 settings.entities_quantity,
                 &time,
                 xy + transform.right().xy().normalize() * particle_distance,
@@ -91,6 +103,7 @@ pub fn control(
             );
             spawn_rotation_thruster_cone(
                 &mut commands,
+                &mut rng.particles,
                 settings.entities_quantity,
                 &time,
                 xy + transform.left().xy().normalize() * particle_distance,
@@ -108,8 +121,10 @@ pub fn control(
                 Vec2 { x: 3000.0, y: 0.0 }.rotate(local_forward) + velocity.linvel,
                 Laser {
                     origin: LaserOrigin::Player,
-                    damage: 1000.0,
+                    shooter: entity,
+                    damage: PLAYER_LASER_DAMAGE,
                     shot_at: time.elapsed_seconds(),
+                    lifetime: LASER_LIFETIME_S,
                 },
             );
             laser_ability.last_shot = Some(time.elapsed_seconds());
@@ -144,10 +159,7 @@ pub fn setup(
             };
             commands.spawn((
                 PlayerMarker,
-                HealthPoints {
-                    max: STARTING_HP,
-                    current: STARTING_HP,
-                },
+                HealthPoints::new(STARTING_HP),
                 Thruster {
                     max_thrust: impulse,
                     current_thrust: 0.0,
@@ -178,7 +190,7 @@ pub fn setup(
                     linvel: player_orbit.velocity,
                     angvel: PI,
                 },
-                ActiveEvents::COLLISION_EVENTS,
+                collisions_handler::ship_contact_events(),
                 AffectedByGravity::default(),
                 game_layer(),
             ));
@@ -0,0 +1,409 @@
+use bevy::prelude::*;
+use bevy_rapier2d::{
+    dynamics::Velocity,
+    geometry::{Collider, ColliderMassProperties},
+};
+
+use crate::{
+    celestial_body::{CelestialBodyMarker, CircularOrbitChain},
+    collisions_handler,
+    course_planner::{scaled_radius, ComputedTrajectory},
+    death::{self, KillKind},
+    gameplay_events::{self, NearMiss, PlayerDamaged, ShipDestroyed, NEAR_MISS_MAX_GAP},
+    healthpoints::DamageCause,
+    landing::Landed,
+    player::PlayerMarker,
+    simulation::SimulationMode,
+    system_sets::AppStage,
+    thruster::Thruster,
+    ui::Score,
+    AppState, GLOBAL_IMPULSE_DURATION_MULT,
+};
+
+// Skimming a body's surface lower than this is a flyby, worth more the lower it gets.
+const FLYBY_ALTITUDE: f32 = 300.0;
+const FLYBY_POINTS: f32 = 30.0;
+// Grazing an enemy ship, worth more the closer it gets.
+const NEAR_MISS_POINTS: f32 = 10.0;
+// A gravity assist is scored on the speed gained between entering and leaving this close to a body,
+// that the thruster didn't provide.
+const ASSIST_RANGE_RADII: f32 = 4.0;
+const ASSIST_MIN_GAIN: f32 = 150.0;
+const ASSIST_POINTS_PER_SPEED: f32 = 0.1;
+// Credited kills this close in time make a multi-kill.
+const MULTI_KILL_WINDOW_S: f32 = 2.0;
+const MULTI_KILL_POINTS: f32 = 15.0;
+// Every so many kills without getting hurt.
+const STREAK_STEP: u32 = 5;
+const STREAK_POINTS: f32 = 25.0;
+// Each award raises the multiplier of the next ones, which decays back to 1 when idle.
+const COMBO_STEP: f32 = 0.25;
+const COMBO_MAX: f32 = 4.0;
+const COMBO_HOLD_S: f32 = 3.0;
+const COMBO_DECAY_PER_S: f32 = 0.5;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Style {
+    Kill(KillKind),
+    MultiKill(u32),
+    Streak(u32),
+    Flyby,
+    NearMiss,
+    GravityAssist,
+}
+
+impl Style {
+    fn base_points(&self) -> f32 {
+        match self {
+            Style::Kill(KillKind::Shot) => 10.0,
+            Style::Kill(KillKind::Rammed) | Style::Kill(KillKind::Lured) => 15.0,
+            Style::Kill(KillKind::Knocked) => 20.0,
+            Style::Kill(KillKind::Unassisted) => 0.0,
+            Style::MultiKill(n) => MULTI_KILL_POINTS * (n - 1) as f32,
+            Style::Streak(n) => STREAK_POINTS * (n / STREAK_STEP) as f32,
+            // These are scored by the caller
+            Style::Flyby | Style::NearMiss | Style::GravityAssist => 0.0,
+        }
+    }
+}
+
+#[derive(Event)]
+pub struct StyleAwarded {
+    pub style: Style,
+    pub points: i32,
+    pub multiplier: f32, // the combo the points were multiplied by
+}
+
+#[derive(Clone, Copy)]
+pub struct Combo {
+    multiplier: f32,
+    last_award_at: f32,
+}
+
+impl Default for Combo {
+    fn default() -> Self {
+        Self {
+            multiplier: 1.0,
+            last_award_at: f32::NEG_INFINITY,
+        }
+    }
+}
+
+impl Combo {
+    pub fn multiplier(&self, now: f32) -> f32 {
+        let idle = (now - self.last_award_at - COMBO_HOLD_S).max(0.0);
+        (self.multiplier - idle * COMBO_DECAY_PER_S).max(1.0)
+    }
+
+    // Returns the multiplied points and the multiplier they got.
+    pub fn award(&mut self, base: f32, now: f32) -> (i32, f32) {
+        let multiplier = self.multiplier(now);
+        self.multiplier = (multiplier + COMBO_STEP).min(COMBO_MAX);
+        self.last_award_at = now;
+        ((base * multiplier).round() as i32, multiplier)
+    }
+}
+
+type FlyingPlayer<'a> = (
+    &'a Transform,
+    &'a Velocity,
+    &'a Thruster,
+    &'a ColliderMassProperties,
+    &'a ComputedTrajectory,
+    Has<Landed>,
+);
+
+struct Encounter {
+    body: Entity,
+    entry_speed: f32,
+    last_speed: f32,
+    thrust_delta_v: f32, // delivered by the thruster since the entry
+}
+
+impl Encounter {
+    fn new(body: Entity, speed: f32) -> Self {
+        Self {
+            body,
+            entry_speed: speed,
+            last_speed: speed,
+            thrust_delta_v: 0.0,
+        }
+    }
+
+    fn track(&mut self, speed: f32, thrust_delta_v: f32) {
+        self.last_speed = speed;
+        self.thrust_delta_v += thrust_delta_v;
+    }
+
+    // Diving into a well and burning back out gains nothing.
+    fn gain(&self) -> f32 {
+        self.last_speed - self.entry_speed - self.thrust_delta_v
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct StyleScoring {
+    pub combo: Combo,
+    pub best_combo: f32,
+    streak: u32,
+    multi_kill: u32,
+    last_kill_at: f32,
+    flyby: Option<f32>, // lowest altitude so far
+    encounter: Option<Encounter>,
+}
+
+impl StyleScoring {
+    fn award(
+        &mut self,
+        style: Style,
+        base: f32,
+        now: f32,
+        score: &mut Score,
+        awards: &mut EventWriter<StyleAwarded>,
+    ) {
+        if base <= 0.0 {
+            return;
+        }
+        let (points, multiplier) = self.combo.award(base, now);
+        self.best_combo = self.best_combo.max(multiplier);
+        match style {
+            Style::Kill(_) => score.kill_points += points,
+            _ => score.style_points += points,
+        }
+        awards.send(StyleAwarded {
+            style,
+            points,
+            multiplier,
+        });
+    }
+}
+
+pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
+    app.add_event::<StyleAwarded>();
+    app.init_resource::<StyleScoring>();
+    app.add_systems(OnEnter(AppState::Game), reset_style_scoring);
+    app.add_systems(
+        schedule,
+        (
+            tick_score_clock,
+            // In the same tick as the kills and hits they score.
+            (score_kills, score_flybys)
+                .after(death::update)
+                .after(collisions_handler::impact_damage_on_ships),
+            score_near_misses.after(gameplay_events::detect_near_misses),
+        )
+            .in_set(AppStage::Simulation)
+            .run_if(in_state(AppState::Game)),
+    );
+}
+
+fn reset_style_scoring(mut commands: Commands) {
+    commands.insert_resource(StyleScoring::default());
+}
+
+// The simulation's time, that only moves when the game does.
+fn tick_score_clock(time: Res<Time>, mut score: ResMut<Score>) {
+    score.game_time += time.delta_seconds();
+}
+
+fn score_kills(
+    time: Res<Time>,
+    mut style: ResMut<StyleScoring>,
+    mut score: ResMut<Score>,
+    mut destroyed: EventReader<ShipDestroyed>,
+    mut damaged: EventReader<PlayerDamaged>,
+    mut awards: EventWriter<StyleAwarded>,
+) {
+    let now = time.elapsed_seconds();
+    if damaged.read().count() > 0 {
+        style.streak = 0;
+    }
+    for kill in destroyed.read().filter_map(|event| event.kill) {
+        if !kill.credited() {
+            continue;
+        }
+        style.award(
+            Style::Kill(kill),
+            Style::Kill(kill).base_points(),
+            now,
+            &mut score,
+            &mut awards,
+        );
+        style.multi_kill = if now - style.last_kill_at <= MULTI_KILL_WINDOW_S {
+            style.multi_kill + 1
+        } else {
+            1
+        };
+        style.last_kill_at = now;
+        if style.multi_kill > 1 {
+            let multi_kill = Style::MultiKill(style.multi_kill);
+            style.award(
+                multi_kill,
+                multi_kill.base_points(),
+                now,
+                &mut score,
+                &mut awards,
+            );
+        }
+        style.streak += 1;
+        if style.streak.is_multiple_of(STREAK_STEP) {
+            let streak = Style::Streak(style.streak);
+            style.award(streak, streak.base_points(), now, &mut score, &mut awards);
+        }
+    }
+}
+
+pub fn near_miss_points(gap: f32) -> f32 {
+    (NEAR_MISS_POINTS * (1.0 - gap / NEAR_MISS_MAX_GAP)).max(0.0)
+}
+
+fn score_near_misses(
+    time: Res<Time>,
+    mut style: ResMut<StyleScoring>,
+    mut score: ResMut<Score>,
+    mut near_misses: EventReader<NearMiss>,
+    mut awards: EventWriter<StyleAwarded>,
+) {
+    let now = time.elapsed_seconds();
+    for near_miss in near_misses.read() {
+        style.award(
+            Style::NearMiss,
+            near_miss_points(near_miss.gap),
+            now,
+            &mut score,
+            &mut awards,
+        );
+    }
+}
+
+pub fn flyby_points(lowest_altitude: f32) -> f32 {
+    (FLYBY_POINTS * (1.0 - lowest_altitude / FLYBY_ALTITUDE)).max(0.0)
+}
+
+fn score_flybys(
+    time: Res<Time>,
+    mut style: ResMut<StyleScoring>,
+    mut score: ResMut<Score>,
+    player: Query<FlyingPlayer, With<PlayerMarker>>,
+    bodies: Query<(Entity, &Transform, &Collider, &CircularOrbitChain), With<CelestialBodyMarker>>,
+    mut damaged: EventReader<PlayerDamaged>,
+    mut awards: EventWriter<StyleAwarded>,
+) {
+    let Ok((pt, pv, thruster, &ColliderMassProperties::Mass(mass), traj, landed)) =
+        player.get_single()
+    else {
+        return;
+    };
+    let now = time.elapsed_seconds();
+    // Touching the ground is no flyby, and no assist.
+    let crashed = damaged
+        .read()
+        .filter(|event| event.hit.cause == DamageCause::Crash)
+        .count()
+        > 0;
+    if landed || crashed {
+        style.flyby = None;
+        style.encounter = None;
+        return;
+    }
+
+    // Flybys, measured along the planned course
+    match (traj.altitude_at(now), style.flyby) {
+        (Some(altitude), lowest) if altitude < FLYBY_ALTITUDE => {
+            style.flyby = Some(lowest.map_or(altitude, |lowest| lowest.min(altitude)));
+        }
+        (Some(_), Some(lowest)) => {
+            style.flyby = None;
+            style.award(
+                Style::Flyby,
+                flyby_points(lowest),
+                now,
+                &mut score,
+                &mut awards,
+            );
+        }
+        _ => {}
+    }
+
+    // Gravity assists, around the nearest body, in the frame of what it revolves around
+    let pos = pt.translation.xy();
+    let nearest = bodies
+        .iter()
+        .map(|(entity, t, collider, chain)| {
+            let radius = scaled_radius(collider, t);
+            (entity, pos.distance(t.translation.xy()) / radius, chain)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1))
+        .filter(|&(_, radii, _)| radii < ASSIST_RANGE_RADII);
+    let ongoing = style.encounter.take();
+    match (nearest, ongoing) {
+        (Some((body, _, chain)), ongoing) => {
+            let speed = (pv.linvel - chain.parent_velocity()).length();
+            let mut encounter = match ongoing {
+                Some(encounter) if encounter.body == body => encounter,
+                _ => Encounter::new(body, speed),
+            };
+            let thrust_delta_v =
+                thruster.current_thrust * time.delta_seconds() * GLOBAL_IMPULSE_DURATION_MULT
+                    / mass;
+            encounter.track(speed, thrust_delta_v);
+            style.encounter = Some(encounter);
+        }
+        (None, Some(encounter)) if encounter.gain() >= ASSIST_MIN_GAIN => {
+            style.award(
+                Style::GravityAssist,
+                encounter.gain() * ASSIST_POINTS_PER_SPEED,
+                now,
+                &mut score,
+                &mut awards,
+            );
+        }
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::entity::Entity;
+
+    use super::{
+        flyby_points, near_miss_points, Combo, Encounter, COMBO_HOLD_S, COMBO_MAX, COMBO_STEP,
+    };
+
+    #[test]
+    fn combo_builds_up_then_decays() {
+        let mut combo = Combo::default();
+        assert_eq!(combo.award(10.0, 0.0), (10, 1.0));
+        assert_eq!(combo.award(10.0, 1.0), (13, 1.0 + COMBO_STEP));
+        for i in 0..20 {
+            combo.award(10.0, 1.0 + i as f32 * 0.1);
+        }
+        assert_eq!(combo.multiplier(3.0), COMBO_MAX);
+        // Held for a while, then back down to 1
+        assert_eq!(combo.multiplier(2.9 + COMBO_HOLD_S), COMBO_MAX);
+        assert!(combo.multiplier(4.0 + COMBO_HOLD_S) < COMBO_MAX);
+        assert_eq!(combo.multiplier(100.0), 1.0);
+    }
+
+    #[test]
+    fn only_unpowered_speed_gains_are_assists() {
+        // Coasting through, slingshot.
+        let mut slingshot = Encounter::new(Entity::from_raw(1), 500.0);
+        slingshot.track(900.0, 0.0);
+        slingshot.track(800.0, 0.0);
+        assert_eq!(slingshot.gain(), 300.0);
+        // Falling in, then burning back out to a bit more than the entry speed.
+        let mut dive = Encounter::new(Entity::from_raw(1), 500.0);
+        dive.track(900.0, 0.0);
+        dive.track(600.0, 400.0);
+        assert!(dive.gain() < 0.0);
+    }
+
+    #[test]
+    fn closer_passes_score_more() {
+        assert!(flyby_points(10.0) > flyby_points(200.0));
+        assert_eq!(flyby_points(1000.0), 0.0);
+        assert!(near_miss_points(5.0) > near_miss_points(80.0));
+    }
+}
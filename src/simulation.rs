@@ -0,0 +1,289 @@
+use bevy::{ecs::schedule::ScheduleLabel, prelude::*, utils::intern::Interned};
+use bevy_rapier2d::plugin::TimestepMode;
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::AppState;
+
+pub const FIXED_TIMESTEP: f32 = 1.0 / 60.0;
+const PHYSICS_SUBSTEPS: usize = 2;
+const REALTIME_MAX_DT: f32 = 1.0 / 20.0;
+const PARTICLES_SEED_SALT: u64 = 0x9e37_79b9_7f4a_7c15;
+
+#[derive(Resource, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SimulationMode {
+    // The simulation runs in `Update` and integrates over the frame's delta time.
+    Realtime,
+    // The simulation runs in `FixedUpdate` at `FIXED_TIMESTEP` and every random draw derives from `seed`.
+    // With the same seed and the same inputs, two runs are bit-identical.
+    Deterministic { seed: u64 },
+}
+
+impl SimulationMode {
+    // Launching the game with `--seed <n>` enables the deterministic mode.
+    pub fn from_args() -> Self {
+        let args = std::env::args().collect::<Vec<String>>();
+        let seed = args
+            .iter()
+            .position(|arg| arg == "--seed")
+            .and_then(|i| args.get(i + 1));
+        match seed.map(|s| s.parse::<u64>()) {
+            Some(Ok(seed)) => SimulationMode::Deterministic { seed },
+            Some(Err(e)) => {
+                error!(
+                    "Invalid --seed value, falling back to realtime simulation: {}",
+                    e
+                );
+                SimulationMode::Realtime
+            }
+            None => SimulationMode::Realtime,
+        }
+    }
+
+    // The schedule gameplay and physics systems run in.
+    pub fn schedule(&self) -> Interned<dyn ScheduleLabel> {
+        match self {
+            SimulationMode::Realtime => Update.intern(),
+            SimulationMode::Deterministic { .. } => FixedUpdate.intern(),
+        }
+    }
+
+    // Rapier runs after our systems in `PostUpdate`, or at the end of each `FixedUpdate` tick.
+    pub fn physics_schedule(&self) -> Interned<dyn ScheduleLabel> {
+        match self {
+            SimulationMode::Realtime => PostUpdate.intern(),
+            SimulationMode::Deterministic { .. } => FixedUpdate.intern(),
+        }
+    }
+
+    pub fn timestep_mode(&self) -> TimestepMode {
+        match self {
+            SimulationMode::Realtime => TimestepMode::Variable {
+                max_dt: REALTIME_MAX_DT,
+                time_scale: 1.0,
+                substeps: PHYSICS_SUBSTEPS,
+            },
+            SimulationMode::Deterministic { .. } => TimestepMode::Fixed {
+                dt: FIXED_TIMESTEP,
+                substeps: PHYSICS_SUBSTEPS,
+            },
+        }
+    }
+}
+
+// How long each substep of a Rapier step lasted, given the step's `dt`.
+// Contact force events report impulses divided by this.
+pub fn physics_substep_dt(step_dt: f32) -> f32 {
+    step_dt / PHYSICS_SUBSTEPS as f32
+}
+
+// Gameplay draws (e.g. wave spawns) and visual draws (particles) use separate streams,
+// so that the amount of particles on screen can't change where enemies spawn.
+#[derive(Resource)]
+pub struct SimulationRng {
+    pub gameplay: StdRng,
+    pub particles: StdRng,
+}
+
+impl SimulationRng {
+    pub fn new(mode: SimulationMode) -> Self {
+        match mode {
+            SimulationMode::Realtime => Self {
+                gameplay: StdRng::from_entropy(),
+                particles: StdRng::from_entropy(),
+            },
+            SimulationMode::Deterministic { seed } => Self {
+                gameplay: StdRng::seed_from_u64(seed),
+                particles: StdRng::seed_from_u64(seed ^ PARTICLES_SEED_SALT),
+            },
+        }
+    }
+}
+
+pub fn setup(app: &mut App, mode: SimulationMode) {
+    if let SimulationMode::Deterministic { seed } = mode {
+        info!("Deterministic simulation, seed: {}", seed);
+    }
+    app.insert_resource(mode);
+    app.insert_resource(SimulationRng::new(mode));
+    app.add_systems(OnEnter(AppState::Game), reset_simulation);
+}
+
+fn reset_simulation(mut commands: Commands, mode: Res<SimulationMode>) {
+    commands.insert_resource(SimulationRng::new(*mode));
+    if let SimulationMode::Deterministic { .. } = *mode {
+        // Restart the fixed clock so that every run ticks through the same elapsed times.
+        commands.insert_resource(Time::<Fixed>::from_seconds(FIXED_TIMESTEP as f64));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use bevy::{prelude::*, time::TimeUpdateStrategy};
+    use bevy_rapier2d::{
+        dynamics::{RigidBody, Velocity},
+        geometry::{Collider, ColliderMassProperties},
+        plugin::{NoUserData, RapierConfiguration, RapierPhysicsPlugin},
+    };
+    use rand::Rng;
+
+    use super::{SimulationMode, SimulationRng, FIXED_TIMESTEP};
+    use crate::{
+        alien_ship::AlienShipMarker,
+        celestial_body::{self, CelestialBodyMarker, CircularOrbitChain, CircularOrbitDef},
+        collisions_handler::{self, ShipRammedShip},
+        gravity::{self, AffectedByGravity, AttractingBody},
+        impulses_aggregator, system_sets, thruster,
+        thruster::Thruster,
+        AppState,
+    };
+
+    #[derive(Resource, Default)]
+    struct Rams(usize);
+
+    fn count_rams(mut rams: EventReader<ShipRammedShip>, mut count: ResMut<Rams>) {
+        count.0 += rams.read().count();
+    }
+
+    // Runs `ticks` fixed ticks, `frames_per_tick` frames apart.
+    // Returns the ships' positions and how many ram impacts were routed.
+    fn run(seed: u64, ticks: u32, frames_per_tick: u32) -> (Vec<[u32; 2]>, usize) {
+        let mode = SimulationMode::Deterministic { seed };
+        let timestep = Duration::from_secs_f64(FIXED_TIMESTEP as f64);
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin));
+        app.add_state::<AppState>();
+        app.insert_resource(NextState(Some(AppState::Game)));
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(
+            timestep / frames_per_tick,
+        ));
+        app.insert_resource(Time::<Fixed>::from_duration(timestep));
+        app.insert_resource(mode);
+        app.insert_resource(RapierConfiguration {
+            gravity: Vec2::ZERO,
+            timestep_mode: mode.timestep_mode(),
+            ..default()
+        });
+        app.add_plugins(
+            RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(1.0)
+                .in_schedule(mode.physics_schedule()),
+        );
+        system_sets::setup(&mut app);
+        impulses_aggregator::setup(&mut app);
+        gravity::setup(&mut app);
+        collisions_handler::setup(&mut app);
+        app.insert_resource(Rams::default());
+        app.add_systems(
+            mode.schedule(),
+            (
+                thruster::update,
+                gravity::update,
+                celestial_body::update,
+                (collisions_handler::route_collisions, count_rams).chain(),
+            )
+                .in_set(system_sets::AppStage::Simulation),
+        );
+
+        app.world.spawn((
+            CelestialBodyMarker,
+            CircularOrbitChain {
+                origin: Vec2::ZERO,
+                chain: vec![CircularOrbitDef {
+                    theta: 0.0,
+                    radius: 3000.0,
+                    freq: 0.1,
+                }
+                .into()],
+            },
+            TransformBundle::default(),
+            RigidBody::Fixed,
+            AttractingBody,
+            Collider::ball(500.0),
+            ColliderMassProperties::Mass(5e7),
+        ));
+
+        let mut rng = SimulationRng::new(mode);
+        let ships = (0..20)
+            .map(|_| {
+                let pos = Vec2::new(
+                    rng.gameplay.gen_range(-5000.0..5000.0),
+                    rng.gameplay.gen_range(-5000.0..5000.0),
+                );
+                let heading = rng.gameplay.gen_range(0.0..std::f32::consts::TAU);
+                app.world
+                    .spawn((
+                        TransformBundle::from_transform(
+                            Transform::from_translation(pos.extend(0.0))
+                                .with_rotation(Quat::from_rotation_z(heading)),
+                        ),
+                        RigidBody::Dynamic,
+                        Collider::ball(32.0),
+                        ColliderMassProperties::Mass(1.0),
+                        Velocity::linear(Vec2::new(
+                            rng.gameplay.gen_range(-500.0..500.0),
+                            rng.gameplay.gen_range(-500.0..500.0),
+                        )),
+                        AffectedByGravity::default(),
+                        AlienShipMarker,
+                        collisions_handler::ship_contact_events(),
+                        Thruster {
+                            max_thrust: 8.0,
+                            current_thrust: 4.0,
+                            rampup_rate: 2.0,
+                            shutoff_rate: 8.0,
+                            ignition_thrust: 4.0,
+                        },
+                    ))
+                    .id()
+            })
+            .collect::<Vec<Entity>>();
+
+        // Two ships bound to ram each other, far from the rest.
+        for (x, vx) in [(20000.0, 300.0), (20300.0, -300.0)] {
+            app.world.spawn((
+                TransformBundle::from_transform(Transform::from_xyz(x, 0.0, 0.0)),
+                RigidBody::Dynamic,
+                Collider::ball(32.0),
+                ColliderMassProperties::Mass(1.0),
+                Velocity::linear(Vec2::new(vx, 0.0)),
+                AlienShipMarker,
+                collisions_handler::ship_contact_events(),
+            ));
+        }
+
+        // The fixed clock only moves forward by whole timesteps.
+        while app.world.resource::<Time<Fixed>>().elapsed() < timestep * ticks {
+            app.update();
+        }
+
+        let positions = ships
+            .iter()
+            .map(|&ship| {
+                let p = app.world.get::<Transform>(ship).unwrap().translation;
+                [p.x.to_bits(), p.y.to_bits()]
+            })
+            .collect();
+        (positions, app.world.resource::<Rams>().0)
+    }
+
+    #[test]
+    fn same_seed_gives_bit_identical_positions() {
+        let a = run(42, 300, 1);
+        let b = run(42, 300, 1);
+        assert_eq!(a, b);
+        assert_ne!(a.0, run(42, 0, 1).0, "ships should have moved");
+        assert!(a.1 > 0, "the rams should have been seen");
+    }
+
+    #[test]
+    fn frame_pacing_does_not_change_the_simulation() {
+        // Frames without a tick in between mustn't lose the physics events.
+        assert_eq!(run(42, 300, 1), run(42, 300, 3));
+    }
+
+    #[test]
+    fn different_seeds_diverge() {
+        assert_ne!(run(1, 10, 1).0, run(2, 10, 1).0);
+    }
+}
@@ -0,0 +1,412 @@
+use std::fmt;
+
+use bevy::{
+    asset::{io::Reader, AssetLoader, AsyncReadExt, LoadContext},
+    prelude::*,
+    utils::BoxedFuture,
+};
+use serde::Deserialize;
+
+use crate::{
+    celestial_body::{OrbitDef, StarterPlanetMarker},
+    star_system_generator,
+    ui::GameSettings,
+    AppState,
+};
+
+pub const DEFAULT_STAR_SYSTEM_PATH: &str = "systems/default.system.ron";
+
+// A star system as described in a `.system.ron` asset file.
+// Angles are in radians, orbit frequencies in radians per second.
+#[derive(Asset, TypePath, Deserialize, Clone, Debug)]
+pub struct StarSystemDef {
+    pub roots: Vec<RootBodyDef>,
+}
+
+// A node of the orbit hierarchy sitting at a fixed position.
+#[derive(Deserialize, Clone, Debug)]
+pub struct RootBodyDef {
+    pub name: String,
+    pub position: Vec2,
+    #[serde(default)]
+    pub body: Option<BodyDef>,
+    #[serde(default)]
+    pub children: Vec<OrbitingBodyDef>,
+}
+
+// A node of the orbit hierarchy orbiting its parent.
+// Nodes without a body are empty barycenters (e.g. the center of a binary pair).
+#[derive(Deserialize, Clone, Debug)]
+pub struct OrbitingBodyDef {
+    pub name: String,
+    pub orbit: OrbitDef,
+    #[serde(default)]
+    pub body: Option<BodyDef>,
+    #[serde(default)]
+    pub children: Vec<OrbitingBodyDef>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+pub struct BodyDef {
+    pub sprite: String,
+    pub scale: f32,
+    pub radius: f32,
+    pub mass: f32,
+    #[serde(default)]
+    pub starter: Option<StarterPlanetMarker>,
+}
+
+impl BodyDef {
+    // How far from its center the body needs room: its surface, or the player's starting orbit.
+    pub fn extent(&self) -> f32 {
+        let radius = self.radius * self.scale;
+        self.starter
+            .as_ref()
+            .map_or(radius, |starter| radius.max(starter.orbit_radius))
+    }
+}
+
+#[derive(Resource)]
+pub struct StarSystemHandle(pub Handle<StarSystemDef>);
+
+#[derive(Debug)]
+pub enum StarSystemLoadError {
+    Io(std::io::Error),
+    Parse(ron::error::SpannedError),
+    Invalid { node: String, reason: String },
+}
+
+impl fmt::Display for StarSystemLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StarSystemLoadError::Io(e) => write!(f, "could not read star system file: {}", e),
+            StarSystemLoadError::Parse(e) => write!(f, "malformed star system file: {}", e),
+            StarSystemLoadError::Invalid { node, reason } => {
+                write!(f, "invalid star system, at `{}`: {}", node, reason)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StarSystemLoadError {}
+
+impl From<std::io::Error> for StarSystemLoadError {
+    fn from(value: std::io::Error) -> Self {
+        StarSystemLoadError::Io(value)
+    }
+}
+
+impl From<ron::error::SpannedError> for StarSystemLoadError {
+    fn from(value: ron::error::SpannedError) -> Self {
+        StarSystemLoadError::Parse(value)
+    }
+}
+
+impl StarSystemDef {
+    pub fn validate(&self) -> Result<(), StarSystemLoadError> {
+        if self.roots.is_empty() {
+            return Err(invalid(
+                "<root>",
+                "a star system needs at least one root body",
+            ));
+        }
+        let mut starters = vec![];
+        for root in self.roots.iter() {
+            check_finite(&root.name, "position", root.position.x)?;
+            check_finite(&root.name, "position", root.position.y)?;
+            validate_node(&root.name, &root.body, &root.children, &mut starters)?;
+            check_spacing(&root.name, &root.body, &root.children)?;
+        }
+        match starters.len() {
+            0 => Err(invalid(
+                "<root>",
+                "no body has a `starter`, the player would have nowhere to spawn",
+            )),
+            1 => Ok(()),
+            _ => Err(invalid(
+                &starters.join(", "),
+                "only one body can have a `starter`",
+            )),
+        }
+    }
+}
+
+fn validate_node(
+    path: &str,
+    body: &Option<BodyDef>,
+    children: &[OrbitingBodyDef],
+    starters: &mut Vec<String>,
+) -> Result<(), StarSystemLoadError> {
+    if let Some(body) = body {
+        if body.sprite.is_empty() {
+            return Err(invalid(path, "`sprite` can't be empty"));
+        }
+        check_positive(path, "scale", body.scale)?;
+        check_positive(path, "radius", body.radius)?;
+        check_positive(path, "mass", body.mass)?;
+        if let Some(starter) = &body.starter {
+            check_positive(path, "starter.orbit_radius", starter.orbit_radius)?;
+            check_finite(path, "starter.theta", starter.theta)?;
+            check_finite(path, "starter.velocity", starter.velocity.x)?;
+            check_finite(path, "starter.velocity", starter.velocity.y)?;
+            starters.push(path.to_string());
+        }
+    } else if children.is_empty() {
+        return Err(invalid(path, "a node needs either a `body` or `children`"));
+    }
+    for child in children.iter() {
+        let child_path = format!("{}/{}", path, child.name);
+        validate_orbit(&child_path, &child.orbit)?;
+        validate_node(&child_path, &child.body, &child.children, starters)?;
+    }
+    Ok(())
+}
+
+fn validate_orbit(path: &str, orbit: &OrbitDef) -> Result<(), StarSystemLoadError> {
+    match orbit {
+        OrbitDef::Circular(orbit) => {
+            check_positive(path, "orbit.radius", orbit.radius)?;
+            check_finite(path, "orbit.theta", orbit.theta)?;
+            check_finite(path, "orbit.freq", orbit.freq)
+        }
+        OrbitDef::Keplerian(orbit) => {
+            if !(0.0..1.0).contains(&orbit.eccentricity) {
+                return Err(invalid(
+                    path,
+                    &format!(
+                        "`orbit.eccentricity` must be in [0, 1), got {}",
+                        orbit.eccentricity
+                    ),
+                ));
+            }
+            check_positive(path, "orbit.semi_major_axis", orbit.semi_major_axis)?;
+            check_finite(
+                path,
+                "orbit.argument_of_periapsis",
+                orbit.argument_of_periapsis,
+            )?;
+            check_finite(path, "orbit.mean_anomaly", orbit.mean_anomaly)?;
+            check_finite(path, "orbit.freq", orbit.freq)
+        }
+    }
+}
+
+// Checks that sibling orbits can't cross each other, and returns how far the node's bodies reach from its center.
+// Siblings sharing the same circular orbit are allowed as long as they stay apart (e.g. a binary pair).
+fn check_spacing(
+    path: &str,
+    body: &Option<BodyDef>,
+    children: &[OrbitingBodyDef],
+) -> Result<f32, StarSystemLoadError> {
+    let extent = body.as_ref().map_or(0.0, BodyDef::extent);
+    let mut bands = vec![];
+    for child in children.iter() {
+        let child_path = format!("{}/{}", path, child.name);
+        let reach = check_spacing(&child_path, &child.body, &child.children)?;
+        let (periapsis, apoapsis) = child.orbit.apsides();
+        if periapsis - reach <= extent {
+            return Err(invalid(
+                &child_path,
+                "the orbit comes too close to its parent",
+            ));
+        }
+        bands.push((
+            child_path,
+            child.orbit,
+            periapsis - reach,
+            apoapsis + reach,
+            reach,
+        ));
+    }
+    for (i, (path_a, orbit_a, inner_a, outer_a, reach_a)) in bands.iter().enumerate() {
+        for (path_b, orbit_b, inner_b, outer_b, reach_b) in bands.iter().skip(i + 1) {
+            let overlapping = inner_a < outer_b && inner_b < outer_a;
+            if overlapping && !co_orbital(orbit_a, orbit_b, reach_a + reach_b) {
+                return Err(invalid(
+                    &format!("{}, {}", path_a, path_b),
+                    "the orbits overlap",
+                ));
+            }
+        }
+    }
+    Ok(bands
+        .iter()
+        .map(|(_, _, _, outer, _)| *outer)
+        .fold(extent, f32::max))
+}
+
+// Two bodies on the same circular orbit never get closer than the chord between them.
+fn co_orbital(a: &OrbitDef, b: &OrbitDef, min_distance: f32) -> bool {
+    match (a, b) {
+        (OrbitDef::Circular(a), OrbitDef::Circular(b)) => {
+            a.radius == b.radius
+                && a.freq == b.freq
+                && a.offset(0.0).distance(b.offset(0.0)) > min_distance
+        }
+        _ => false,
+    }
+}
+
+fn check_finite(path: &str, field: &str, value: f32) -> Result<(), StarSystemLoadError> {
+    if value.is_finite() {
+        Ok(())
+    } else {
+        Err(invalid(
+            path,
+            &format!("`{}` must be a finite number, got {}", field, value),
+        ))
+    }
+}
+
+fn check_positive(path: &str, field: &str, value: f32) -> Result<(), StarSystemLoadError> {
+    if value.is_finite() && value > 0.0 {
+        Ok(())
+    } else {
+        Err(invalid(
+            path,
+            &format!("`{}` must be a positive number, got {}", field, value),
+        ))
+    }
+}
+
+fn invalid(node: &str, reason: &str) -> StarSystemLoadError {
+    StarSystemLoadError::Invalid {
+        node: node.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+#[derive(Default)]
+pub struct StarSystemLoader;
+
+impl AssetLoader for StarSystemLoader {
+    type Asset = StarSystemDef;
+    type Settings = ();
+    type Error = StarSystemLoadError;
+
+    fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<Self::Asset, Self::Error>> {
+        Box::pin(async move {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).await?;
+            let system = ron::de::from_bytes::<StarSystemDef>(&bytes)?;
+            system.validate()?;
+            Ok(system)
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["system.ron"]
+    }
+}
+
+pub fn setup(app: &mut App) {
+    app.init_asset::<StarSystemDef>();
+    app.init_asset_loader::<StarSystemLoader>();
+    app.add_systems(Startup, load_default_star_system);
+    app.add_systems(OnEnter(AppState::Game), select_star_system);
+}
+
+fn load_default_star_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.insert_resource(StarSystemHandle(
+        asset_server.load(DEFAULT_STAR_SYSTEM_PATH),
+    ));
+}
+
+fn select_star_system(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    settings: Res<GameSettings>,
+    mut star_systems: ResMut<Assets<StarSystemDef>>,
+) {
+    let handle = match settings.system_seed {
+        Some(seed) => match star_system_generator::generate(seed) {
+            Ok(system) => star_systems.add(system),
+            Err(e) => {
+                error!(
+                    "Could not generate a star system from seed {}, using the default one: {}",
+                    seed, e
+                );
+                asset_server.load(DEFAULT_STAR_SYSTEM_PATH)
+            }
+        },
+        None => asset_server.load(DEFAULT_STAR_SYSTEM_PATH),
+    };
+    commands.insert_resource(StarSystemHandle(handle));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::StarSystemDef;
+
+    fn parse(source: &str) -> StarSystemDef {
+        ron::de::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn default_system_is_valid() {
+        let system = parse(include_str!("../assets/systems/default.system.ron"));
+        assert!(system.validate().is_ok());
+    }
+
+    fn system_with_moons(moons: &str) -> StarSystemDef {
+        parse(&format!(
+            r#"(roots: [(
+                name: "sun",
+                position: (0.0, 0.0),
+                body: Some((sprite: "sun.webp", scale: 1.0, radius: 500.0, mass: 1e7,
+                    starter: Some((orbit_radius: 1500.0, theta: 0.0, velocity: (0.0, 0.0))))),
+                children: [{}],
+            )])"#,
+            moons
+        ))
+    }
+
+    fn moon(name: &str, orbit: &str) -> String {
+        format!(
+            r#"(name: "{}", orbit: {}, body: Some((sprite: "moon.webp", scale: 1.0, radius: 100.0, mass: 1e5)))"#,
+            name, orbit
+        )
+    }
+
+    #[test]
+    fn overlapping_orbits_are_rejected() {
+        let inner = moon("inner", "(theta: 0.0, radius: 5000.0, freq: 0.1)");
+        let outer = moon("outer", "(theta: 0.0, radius: 5150.0, freq: 0.05)");
+        let apart = moon("apart", "(theta: 0.0, radius: 8000.0, freq: 0.05)");
+        assert!(system_with_moons(&format!("{}, {}", inner, apart))
+            .validate()
+            .is_ok());
+        assert!(system_with_moons(&format!("{}, {}", inner, outer))
+            .validate()
+            .is_err());
+        // An eccentric orbit crossing the inner one.
+        let comet = moon(
+            "comet",
+            "(eccentricity: 0.5, semi_major_axis: 8000.0, argument_of_periapsis: 0.0, mean_anomaly: 0.0, freq: 0.01)",
+        );
+        assert!(system_with_moons(&format!("{}, {}", inner, comet))
+            .validate()
+            .is_err());
+        // Too close to the starting orbit around the sun.
+        let grazing = moon("grazing", "(theta: 0.0, radius: 1550.0, freq: 0.1)");
+        assert!(system_with_moons(&grazing).validate().is_err());
+    }
+
+    #[test]
+    fn co_orbital_bodies_must_stay_apart() {
+        let a = moon("a", "(theta: 0.0, radius: 5000.0, freq: 0.1)");
+        let b = moon("b", "(theta: 3.1415927, radius: 5000.0, freq: 0.1)");
+        let c = moon("c", "(theta: 0.01, radius: 5000.0, freq: 0.1)");
+        assert!(system_with_moons(&format!("{}, {}", a, b))
+            .validate()
+            .is_ok());
+        assert!(system_with_moons(&format!("{}, {}", a, c))
+            .validate()
+            .is_err());
+    }
+}
@@ -0,0 +1,314 @@
+use std::ops::Range;
+
+use bevy::prelude::*;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    celestial_body::{CircularOrbitDef, KeplerianOrbitDef, OrbitDef, StarterPlanetMarker},
+    gravity::gravity_formula,
+    star_system::{BodyDef, OrbitingBodyDef, RootBodyDef, StarSystemDef, StarSystemLoadError},
+};
+
+// All the planet sprites have the same size.
+const SPRITE_RADIUS: f32 = 500.0;
+const PRIMARY_SPRITES: [&str; 4] = [
+    "planet_big.webp",
+    "planet_big_1.webp",
+    "planet_pink_shiny_rock.webp",
+    "planet_blue_with_clouds.webp",
+];
+const SMALL_SPRITES: [&str; 6] = [
+    "planet_small_0.webp",
+    "planet_small_1.webp",
+    "planet_small_2.webp",
+    "planet_small_3.webp",
+    "planet_blue_shiny_rock.webp",
+    "planet_dark_rocky.webp",
+];
+const NAME_SYLLABLES: [&str; 16] = [
+    "ka", "lo", "mi", "ra", "te", "vu", "zen", "or", "bi", "ta", "nu", "xi", "el", "pha", "dor",
+    "sa",
+];
+
+const PRIMARIES_COUNT: Range<usize> = 2..5;
+const PRIMARY_MASS: Range<f32> = 4e7..8e7;
+const PRIMARY_SCALE: Range<f32> = 3.0..5.0;
+const PRIMARY_SPACING: f32 = 20000.0;
+const PRIMARY_PLACEMENT_STEP: f32 = 5000.0;
+const SATELLITES_COUNT: Range<usize> = 1..5;
+const SATELLITE_GAP: Range<f32> = 6000.0..16000.0;
+const PLANET_MASS: Range<f32> = 2e6..2e7;
+const PLANET_SCALE: Range<f32> = 0.5..1.5;
+const MOONS_COUNT: Range<usize> = 0..3;
+const MOON_MASS: Range<f32> = 5e5..3e6;
+const MOON_SCALE: Range<f32> = 0.3..0.6;
+const MOON_GAP: Range<f32> = 2000.0..5000.0;
+const BINARY_PROBABILITY: f64 = 0.2;
+const BINARY_GAP: Range<f32> = 300.0..1500.0;
+const BINARY_FREQ: Range<f32> = 0.5..1.2;
+const ECCENTRIC_PROBABILITY: f64 = 0.3;
+const ECCENTRICITY: Range<f32> = 0.05..0.25;
+const STARTER_MASS: Range<f32> = 3e6..6e6;
+const STARTER_SCALE: f32 = 0.5;
+// The player starts on a slightly sub-circular orbit, at this many radii from the surface.
+const STARTER_ORBIT_RADIUS_FACTOR: f32 = 6.0;
+const STARTER_ORBIT_SPEED_FACTOR: f32 = 0.8;
+// Bodies move on rails, much slower than a real orbit would be, so the player can catch up with them.
+const ORBIT_FREQ_FACTOR: f32 = 0.15;
+const MAX_ATTEMPTS: u32 = 16;
+
+// Builds a star system from a seed. The same seed always gives the same system.
+pub fn generate(seed: u64) -> Result<StarSystemDef, StarSystemLoadError> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let mut attempts = 1;
+    loop {
+        let system = generate_candidate(&mut rng);
+        match system.validate() {
+            Ok(()) => return Ok(system),
+            Err(e) if attempts < MAX_ATTEMPTS => {
+                debug!("Rejected a generated star system: {}", e);
+                attempts += 1;
+            }
+            Err(e) => return Err(e),
+        }
+    }
+}
+
+// A generated subtree, centered on its parent's position, and how far it reaches.
+struct Subtree {
+    name: String,
+    body: Option<BodyDef>,
+    children: Vec<OrbitingBodyDef>,
+    reach: f32,
+}
+
+fn generate_candidate(rng: &mut StdRng) -> StarSystemDef {
+    let primaries_count = rng.gen_range(PRIMARIES_COUNT);
+    let starter_primary = rng.gen_range(0..primaries_count);
+    let mut roots: Vec<(RootBodyDef, f32)> = vec![];
+    for i in 0..primaries_count {
+        let primary = gen_primary(rng, i == starter_primary);
+        let position = place_primary(rng, &roots, primary.reach);
+        roots.push((
+            RootBodyDef {
+                name: primary.name,
+                position,
+                body: primary.body,
+                children: primary.children,
+            },
+            primary.reach,
+        ));
+    }
+    StarSystemDef {
+        roots: roots.into_iter().map(|(root, _)| root).collect(),
+    }
+}
+
+// Primaries don't move, we keep their whole systems apart.
+fn place_primary(rng: &mut StdRng, placed: &[(RootBodyDef, f32)], reach: f32) -> Vec2 {
+    let direction = Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU));
+    let mut distance = 0.0;
+    loop {
+        let position = direction * distance;
+        if placed.iter().all(|(root, other_reach)| {
+            root.position.distance(position) > reach + other_reach + PRIMARY_SPACING
+        }) {
+            return position;
+        }
+        distance += PRIMARY_PLACEMENT_STEP;
+    }
+}
+
+fn gen_primary(rng: &mut StdRng, with_starter: bool) -> Subtree {
+    let name = gen_name(rng);
+    let body = gen_body(rng, &PRIMARY_SPRITES, PRIMARY_MASS, PRIMARY_SCALE);
+    let satellites_count = rng.gen_range(SATELLITES_COUNT);
+    let starter_index = with_starter.then(|| rng.gen_range(0..satellites_count));
+    let satellites = (0..satellites_count)
+        .map(|i| {
+            let name = format!("{} {}", name, roman_numeral(i + 1));
+            if starter_index == Some(i) {
+                gen_starter_planet(rng, name)
+            } else if rng.gen_bool(BINARY_PROBABILITY) {
+                gen_binary(rng, name)
+            } else {
+                gen_planet(rng, name)
+            }
+        })
+        .collect();
+    let (children, reach) = arrange_orbits(rng, &body, satellites, SATELLITE_GAP);
+    Subtree {
+        name,
+        body: Some(body),
+        children,
+        reach,
+    }
+}
+
+fn gen_planet(rng: &mut StdRng, name: String) -> Subtree {
+    let body = gen_body(rng, &SMALL_SPRITES, PLANET_MASS, PLANET_SCALE);
+    let moons = (0..rng.gen_range(MOONS_COUNT))
+        .map(|i| {
+            let moon = gen_body(rng, &SMALL_SPRITES, MOON_MASS, MOON_SCALE);
+            Subtree {
+                name: format!("{}{}", name, (b'a' + i as u8) as char),
+                reach: moon.extent(),
+                body: Some(moon),
+                children: vec![],
+            }
+        })
+        .collect();
+    let (children, reach) = arrange_orbits(rng, &body, moons, MOON_GAP);
+    Subtree {
+        name,
+        body: Some(body),
+        children,
+        reach,
+    }
+}
+
+// Two twin bodies on the same circular orbit around an empty barycenter.
+fn gen_binary(rng: &mut StdRng, name: String) -> Subtree {
+    let body = gen_body(rng, &SMALL_SPRITES, PLANET_MASS, MOON_SCALE);
+    let radius = body.extent() * 2.0 + rng.gen_range(BINARY_GAP);
+    let freq = rng.gen_range(BINARY_FREQ);
+    let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+    let reach = radius + body.extent();
+    let children = ["A", "B"]
+        .iter()
+        .enumerate()
+        .map(|(i, suffix)| OrbitingBodyDef {
+            name: format!("{} {}", name, suffix),
+            orbit: OrbitDef::Circular(CircularOrbitDef {
+                theta: theta + i as f32 * std::f32::consts::PI,
+                radius,
+                freq,
+            }),
+            body: Some(body.clone()),
+            children: vec![],
+        })
+        .collect();
+    Subtree {
+        name,
+        body: None,
+        children,
+        reach,
+    }
+}
+
+fn gen_starter_planet(rng: &mut StdRng, name: String) -> Subtree {
+    let mut body = gen_body(rng, &SMALL_SPRITES, STARTER_MASS, PLANET_SCALE);
+    body.scale = STARTER_SCALE;
+    let orbit_radius = body.extent() * STARTER_ORBIT_RADIUS_FACTOR;
+    let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+    let speed = circular_speed(body.mass, orbit_radius) * STARTER_ORBIT_SPEED_FACTOR;
+    body.starter = Some(StarterPlanetMarker {
+        orbit_radius,
+        theta,
+        velocity: Vec2::from_angle(theta).perp() * speed,
+    });
+    Subtree {
+        name,
+        reach: body.extent(),
+        body: Some(body),
+        children: vec![],
+    }
+}
+
+fn gen_body(rng: &mut StdRng, sprites: &[&str], mass: Range<f32>, scale: Range<f32>) -> BodyDef {
+    BodyDef {
+        sprite: sprites.choose(rng).unwrap().to_string(),
+        scale: rng.gen_range(scale),
+        radius: SPRITE_RADIUS,
+        mass: rng.gen_range(mass),
+        starter: None,
+    }
+}
+
+// Lays the subtrees out on orbits of increasing size around `parent`, each one clear of the previous one.
+fn arrange_orbits(
+    rng: &mut StdRng,
+    parent: &BodyDef,
+    subtrees: Vec<Subtree>,
+    gap: Range<f32>,
+) -> (Vec<OrbitingBodyDef>, f32) {
+    let mut inner_edge = parent.extent();
+    let children = subtrees
+        .into_iter()
+        .map(|subtree| {
+            inner_edge += rng.gen_range(gap.clone());
+            let periapsis = inner_edge + subtree.reach;
+            let theta = rng.gen_range(0.0..std::f32::consts::TAU);
+            let orbit = if rng.gen_bool(ECCENTRIC_PROBABILITY) {
+                let eccentricity = rng.gen_range(ECCENTRICITY);
+                let semi_major_axis = periapsis / (1.0 - eccentricity);
+                OrbitDef::Keplerian(KeplerianOrbitDef {
+                    eccentricity,
+                    semi_major_axis,
+                    argument_of_periapsis: theta,
+                    mean_anomaly: rng.gen_range(0.0..std::f32::consts::TAU),
+                    freq: rail_freq(parent.mass, semi_major_axis),
+                })
+            } else {
+                OrbitDef::Circular(CircularOrbitDef {
+                    theta,
+                    radius: periapsis,
+                    freq: rail_freq(parent.mass, periapsis),
+                })
+            };
+            inner_edge = orbit.apsides().1 + subtree.reach;
+            OrbitingBodyDef {
+                name: subtree.name,
+                orbit,
+                body: subtree.body,
+                children: subtree.children,
+            }
+        })
+        .collect();
+    (children, inner_edge)
+}
+
+fn circular_speed(mass: f32, radius: f32) -> f32 {
+    (gravity_formula(radius, mass) * radius).sqrt()
+}
+
+fn rail_freq(mass: f32, radius: f32) -> f32 {
+    circular_speed(mass, radius) / radius * ORBIT_FREQ_FACTOR
+}
+
+fn gen_name(rng: &mut StdRng) -> String {
+    let name: String = (0..rng.gen_range(2..4))
+        .map(|_| *NAME_SYLLABLES.choose(rng).unwrap())
+        .collect();
+    let mut chars = name.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => name,
+    }
+}
+
+fn roman_numeral(n: usize) -> &'static str {
+    ["I", "II", "III", "IV", "V", "VI", "VII", "VIII"][n - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::generate;
+
+    #[test]
+    fn generated_systems_are_valid() {
+        for seed in 0..200 {
+            let system = generate(seed).unwrap();
+            assert!(system.validate().is_ok(), "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn same_seed_same_system() {
+        let a = format!("{:?}", generate(1234).unwrap());
+        let b = format!("{:?}", generate(1234).unwrap());
+        let c = format!("{:?}", generate(1235).unwrap());
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+    }
+}
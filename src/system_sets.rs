@@ -1,4 +1,7 @@
 use bevy::prelude::*;
+use bevy_rapier2d::plugin::PhysicsSet;
+
+use crate::simulation::SimulationMode;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, SystemSet)]
 pub enum AppStage {
@@ -12,8 +15,9 @@ pub enum AppStage {
 }
 
 pub fn setup(app: &mut App) {
+    let schedule = app.world.resource::<SimulationMode>().schedule();
     app.configure_sets(
-        Update,
+        schedule,
         (
             AppStage::AI,
             AppStage::Control,
@@ -23,6 +27,7 @@ pub fn setup(app: &mut App) {
             AppStage::Draw,
             AppStage::DespawnQueue,
         )
-            .chain(),
+            .chain()
+            .before(PhysicsSet::SyncBackend),
     );
 }
@@ -25,6 +25,65 @@ impl Thruster {
     }
 }
 
+// How a thruster's thrust evolves if it keeps burning for `burn_duration` seconds, then is released.
+// The ship's heading is assumed not to change.
+#[derive(Clone, Copy)]
+pub struct ThrustProfile {
+    pub direction: Vec2,
+    pub acceleration_per_thrust: f32,
+    pub initial_thrust: f32,
+    pub max_thrust: f32,
+    pub rampup_rate: f32,
+    pub shutoff_rate: f32,
+    pub burn_duration: f32,
+    pub ignition_delay: f32, // spent turning towards `direction`, engine off
+}
+
+impl ThrustProfile {
+    pub fn with_ignition_delay(mut self, delay: f32) -> Self {
+        self.ignition_delay = delay;
+        self
+    }
+
+    pub fn thrust(&self, t: f32) -> f32 {
+        if t < self.ignition_delay {
+            return 0.0;
+        }
+        let t = t - self.ignition_delay;
+        let burning = t.min(self.burn_duration);
+        let thrust_at_release = if self.burn_duration > 0.0 {
+            (self.initial_thrust + self.rampup_rate * burning).min(self.max_thrust)
+        } else {
+            self.initial_thrust
+        };
+        (thrust_at_release - self.shutoff_rate * (t - burning)).max(0.0)
+    }
+
+    pub fn acceleration(&self, t: f32) -> Vec2 {
+        self.direction * self.thrust(t) * self.acceleration_per_thrust
+    }
+}
+
+impl Thruster {
+    // A zero `burn_duration` means releasing the throttle right away.
+    pub fn profile(&self, direction: Vec2, mass: f32, burn_duration: f32) -> ThrustProfile {
+        ThrustProfile {
+            direction: direction.normalize_or_zero(),
+            acceleration_per_thrust: GLOBAL_IMPULSE_DURATION_MULT / mass,
+            initial_thrust: if burn_duration > 0.0 {
+                self.current_thrust.max(self.ignition_thrust)
+            } else {
+                self.current_thrust
+            },
+            max_thrust: self.max_thrust,
+            rampup_rate: self.rampup_rate,
+            shutoff_rate: self.shutoff_rate,
+            burn_duration,
+            ignition_delay: 0.0,
+        }
+    }
+}
+
 pub fn update(
     time: Res<Time>,
     mut impulses: EventWriter<AddExternalImpulse>,
@@ -45,3 +104,43 @@ pub fn update(
         });
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::Thruster;
+
+    #[test]
+    fn profile_matches_throttling_frame_by_frame() {
+        let dt = 1.0 / 60.0;
+        let mut thruster = Thruster {
+            max_thrust: 32.0,
+            current_thrust: 0.0,
+            rampup_rate: 16.0,
+            shutoff_rate: 64.0,
+            ignition_thrust: 12.0,
+        };
+        let profile = thruster.profile(Vec2::Y, 4.0, 1.0);
+        for frame in 0..120 {
+            if frame < 60 {
+                thruster.throttle(dt);
+            } else {
+                thruster.release(dt);
+            }
+            let t = (frame + 1) as f32 * dt;
+            // `throttle` jumps to the ignition thrust on the first frame instead of ramping up from it.
+            assert!(
+                (profile.thrust(t) - thruster.current_thrust).abs() <= 16.0 * dt + 1e-3,
+                "at {}s: {} != {}",
+                t,
+                profile.thrust(t),
+                thruster.current_thrust
+            );
+        }
+        // Turning first shifts the whole burn.
+        let delayed = profile.with_ignition_delay(0.5);
+        assert_eq!(delayed.thrust(0.4), 0.0);
+        assert_eq!(delayed.thrust(1.0), profile.thrust(0.5));
+    }
+}
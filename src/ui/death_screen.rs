@@ -1,11 +1,8 @@
-use bevy::{prelude::*, utils::Instant};
+use bevy::prelude::*;
 
-use crate::ui::{
-    menu::GameSettings,
-    score::{compute_score, score_multiplier, Score},
-};
+use crate::ui::{menu::GameSettings, score::RunSummary};
 
-use crate::AppState;
+use crate::{death::KillKind, AppState};
 
 const PRIMARY_COLOR: Color = Color::rgb(0.95, 0.95, 0.95);
 
@@ -31,7 +28,7 @@ fn setup_death_screen(
     time: Res<Time>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
-    score: Res<Score>,
+    summary: Res<RunSummary>,
     mut settings: ResMut<GameSettings>,
 ) {
     commands.spawn(Camera2dBundle::default());
@@ -79,7 +76,10 @@ fn setup_death_screen(
 
     let enemies_killed = commands
         .spawn(TextBundle::from_section(
-            format!("Enemies killed : {}", score.enemies_killed),
+            format!(
+                "Enemies killed : {}    Waves : {}",
+                summary.enemies_killed, summary.waves
+            ),
             TextStyle {
                 font_size: 30.0,
                 font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
@@ -89,14 +89,44 @@ fn setup_death_screen(
         ))
         .id();
 
-    let game_duration = Instant::now().duration_since(score.time_game_start);
+    // How the enemies died, when the player got credit for them or not
+    let kill_breakdown = KillKind::ALL
+        .iter()
+        .filter_map(|kind| {
+            let count = summary.kills.get(kind).copied().unwrap_or(0);
+            (count > 0).then(|| format!("{} : {}", kind.label(), count))
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+    let kill_breakdown = commands
+        .spawn(
+            TextBundle::from_section(
+                kill_breakdown,
+                TextStyle {
+                    font_size: 20.0,
+                    font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                    color: PRIMARY_COLOR,
+                },
+            )
+            .with_text_alignment(TextAlignment::Center)
+            .with_style(Style {
+                margin: UiRect {
+                    bottom: Val::Px(10.),
+                    ..Default::default()
+                },
+                ..Default::default()
+            }),
+        )
+        .id();
+
+    let game_duration = summary.duration as u32;
 
     let game_duration = commands
         .spawn(TextBundle::from_section(
             format!(
                 "Game duration : {:02}:{:02}",
-                game_duration.as_secs() / 60,
-                game_duration.as_secs() % 60
+                game_duration / 60,
+                game_duration % 60
             ),
             TextStyle {
                 font_size: 30.0,
@@ -107,11 +137,28 @@ fn setup_death_screen(
         ))
         .id();
 
+    let points = commands
+        .spawn(TextBundle::from_section(
+            format!(
+                "Kills : +{}    Style : +{} ( best combo x{:.2} )    Time : -{}",
+                summary.kill_points,
+                summary.style_points,
+                summary.best_combo.max(1.0),
+                summary.time_penalty,
+            ),
+            TextStyle {
+                font_size: 30.0,
+                font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                color: PRIMARY_COLOR,
+            },
+        ))
+        .id();
+
     let score_multiplier = commands
         .spawn(TextBundle::from_section(
             format!(
                 "Difficulty multiplier : x{} ( {}, {} )",
-                score_multiplier(&settings),
+                summary.difficulty_multiplier,
                 settings.difficulty.as_str(),
                 entities_quantity_label(&settings),
             ),
@@ -126,7 +173,7 @@ fn setup_death_screen(
 
     let final_score = commands
         .spawn(TextBundle::from_section(
-            format!("Final Score : {}", compute_score(&score, &settings)),
+            format!("Final Score : {}", summary.final_score),
             TextStyle {
                 font_size: 60.0,
                 font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
@@ -137,7 +184,9 @@ fn setup_death_screen(
         .id();
 
     commands.entity(death_screen).add_child(enemies_killed);
+    commands.entity(death_screen).add_child(kill_breakdown);
     commands.entity(death_screen).add_child(game_duration);
+    commands.entity(death_screen).add_child(points);
     commands.entity(death_screen).add_child(score_multiplier);
     commands.entity(death_screen).add_child(final_score);
 
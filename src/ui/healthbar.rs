@@ -3,11 +3,12 @@ use bevy::{prelude::*, render::view::RenderLayers, window::PrimaryWindow};
 use bevy_vector_shapes::{painter::ShapePainter, shapes::RectPainter};
 
 use crate::{
-    camera::UI_LAYER, healthpoints::HealthPoints, player::PlayerMarker, system_sets::AppStage,
-    AppState,
+    camera::UI_LAYER, gameplay_events::PlayerDamaged, healthpoints::HealthPoints,
+    player::PlayerMarker, system_sets::AppStage, AppState,
 };
 
 const BAR_SIZE: Vec2 = Vec2 { x: 300.0, y: 25.0 };
+const HIT_FLASH_S: f32 = 0.3;
 
 pub fn setup(app: &mut App) {
     app.add_systems(
@@ -22,7 +23,13 @@ pub fn draw_healthbar(
     mut painter: ShapePainter,
     q_window: Query<&Window, With<PrimaryWindow>>,
     player_hp: Query<&HealthPoints, With<PlayerMarker>>,
+    time: Res<Time>,
+    mut damaged: EventReader<PlayerDamaged>,
+    mut last_hit_at: Local<Option<f32>>,
 ) {
+    if damaged.read().count() > 0 {
+        *last_hit_at = Some(time.elapsed_seconds());
+    }
     let win = q_window.single();
     if let Ok(hp) = player_hp.get_single() {
         painter.set_2d();
@@ -53,7 +60,11 @@ pub fn draw_healthbar(
             0.0,
         ));
         painter.render_layers = Some(RenderLayers::layer(UI_LAYER));
-        painter.color = Color::WHITE;
+        // The outline flashes when the player gets hurt
+        let flash = last_hit_at.map_or(0.0, |t| {
+            1.0 - ((time.elapsed_seconds() - t) / HIT_FLASH_S).clamp(0.0, 1.0)
+        });
+        painter.color = Color::rgb(1.0, 1.0 - flash, 1.0 - flash);
         painter.corner_radii = Vec4::splat(5.0);
         painter.hollow = true;
         painter.thickness = 2.0;
@@ -0,0 +1,68 @@
+use bevy::prelude::*;
+
+use crate::{
+    maneuver::{Autopilot, ManeuverPlan},
+    player::PlayerMarker,
+    AppState,
+};
+
+#[derive(Component)]
+pub struct ManeuverHudText;
+
+pub fn setup(app: &mut App) {
+    app.add_systems(OnEnter(AppState::Game), setup_maneuver_hud);
+    app.add_systems(OnExit(AppState::Game), cleanup_maneuver_hud);
+    app.add_systems(Update, update_maneuver_hud.run_if(in_state(AppState::Game)));
+}
+
+fn setup_maneuver_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                font_size: 20.0,
+                color: Color::rgb(1.0, 0.5, 1.0),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            left: Val::Px(20.0),
+            bottom: Val::Px(60.0),
+            ..default()
+        }),
+        ManeuverHudText,
+    ));
+}
+
+fn cleanup_maneuver_hud(mut commands: Commands, text_query: Query<Entity, With<ManeuverHudText>>) {
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_maneuver_hud(
+    time: Res<Time>,
+    plan: Option<Res<ManeuverPlan>>,
+    autopilot: Query<(), (With<PlayerMarker>, With<Autopilot>)>,
+    mut text_query: Query<&mut Text, With<ManeuverHudText>>,
+) {
+    if let (Ok(mut text), Some(plan)) = (text_query.get_single_mut(), plan) {
+        text.sections[0].value = match plan.nodes.get(plan.selected) {
+            Some(node) => format!(
+                "NODE {}/{}  T-{:.1}s\nPROGRADE {:+.0}  RADIAL {:+.0}{}",
+                plan.selected + 1,
+                plan.nodes.len(),
+                (node.at - time.elapsed_seconds()).max(0.0),
+                node.prograde,
+                node.radial,
+                if !autopilot.is_empty() {
+                    "\nAUTOPILOT ENGAGED"
+                } else {
+                    ""
+                }
+            ),
+            None => String::new(),
+        };
+    }
+}
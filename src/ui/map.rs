@@ -0,0 +1,310 @@
+use bevy::{
+    input::mouse::{MouseMotion, MouseScrollUnit, MouseWheel},
+    prelude::*,
+    render::view::RenderLayers,
+    window::PrimaryWindow,
+};
+use bevy_rapier2d::geometry::Collider;
+use bevy_vector_shapes::{
+    painter::ShapePainter,
+    shapes::{DiscPainter, LinePainter, RectPainter},
+};
+
+use crate::{
+    alien_ship::AlienShipMarker,
+    camera::UI_LAYER,
+    celestial_body::{CelestialBodyMarker, OrbitHierarchy},
+    course_planner::{scaled_radius, ComputedTrajectory},
+    player::PlayerMarker,
+    AppState,
+};
+
+const MAP_ORBIT_SEGMENTS: usize = 128;
+const MAP_FIT_MARGIN: f32 = 0.9;
+const MAP_ZOOM_STEP: f32 = 1.15; // per mouse wheel line
+const MAP_PIXELS_PER_WHEEL_LINE: f32 = 100.0;
+const MAP_MIN_ZOOM: f32 = 1e-5;
+const MAP_MAX_ZOOM: f32 = 1.0;
+const MAP_MIN_BODY_SIZE: f32 = 3.0;
+// Enemies closer than this to one another are drawn as one wave.
+const MAP_CLUSTER_RADIUS: f32 = 5000.0;
+const MAP_BACKGROUND_ALPHA: f32 = 0.85;
+const MAP_ORBITS_ALPHA: f32 = 0.35;
+const MAP_ENEMY_COURSE_ALPHA: f32 = 0.25;
+
+// The full-system map, drawn over the game. The simulation keeps running underneath.
+#[derive(Resource, Default)]
+pub struct MapView {
+    pub open: bool,
+    pan: Vec2, // the view's center, relative to the player so it survives origin rebases
+    zoom: f32, // screen pixels per world unit
+    fit: bool, // zoom out on the whole system on the next frame
+}
+
+pub fn setup(app: &mut App) {
+    app.init_resource::<MapView>();
+    app.add_systems(OnEnter(AppState::Game), reset_map_view);
+    app.add_systems(
+        Update,
+        (toggle_map, pan_and_zoom_map)
+            .chain()
+            .run_if(in_state(AppState::Game)),
+    );
+}
+
+pub fn map_closed(view: Res<MapView>) -> bool {
+    !view.open
+}
+
+fn reset_map_view(mut commands: Commands) {
+    commands.insert_resource(MapView::default());
+}
+
+fn toggle_map(keys: Res<Input<KeyCode>>, mut view: ResMut<MapView>) {
+    if keys.just_pressed(KeyCode::M) {
+        view.open = !view.open;
+        view.fit = view.open;
+    }
+    if view.open && keys.just_pressed(KeyCode::F) {
+        // Focus back on the player
+        view.pan = Vec2::ZERO;
+    }
+}
+
+fn pan_and_zoom_map(
+    mut view: ResMut<MapView>,
+    mouse_buttons: Res<Input<MouseButton>>,
+    mut motion: EventReader<MouseMotion>,
+    mut wheel: EventReader<MouseWheel>,
+) {
+    if !view.open || view.zoom <= 0.0 {
+        motion.clear();
+        wheel.clear();
+        return;
+    }
+    let dragged: Vec2 = motion.read().map(|event| event.delta).sum();
+    if mouse_buttons.pressed(MouseButton::Left) {
+        // Screen y points down
+        let zoom = view.zoom;
+        view.pan -= Vec2::new(dragged.x, -dragged.y) / zoom;
+    }
+    let lines: f32 = wheel
+        .read()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / MAP_PIXELS_PER_WHEEL_LINE,
+        })
+        .sum();
+    view.zoom = (view.zoom * MAP_ZOOM_STEP.powf(lines)).clamp(MAP_MIN_ZOOM, MAP_MAX_ZOOM);
+}
+
+// Groups positions that are within `radius` of each other, directly or through other positions.
+// Returns each group's center, and how far its farthest member is from it.
+pub fn clusters(positions: &[Vec2], radius: f32) -> Vec<(Vec2, f32)> {
+    let mut group_of: Vec<usize> = (0..positions.len()).collect();
+    fn root(group_of: &mut [usize], i: usize) -> usize {
+        let mut i = i;
+        while group_of[i] != i {
+            group_of[i] = group_of[group_of[i]];
+            i = group_of[i];
+        }
+        i
+    }
+    for i in 0..positions.len() {
+        for j in (i + 1)..positions.len() {
+            if positions[i].distance(positions[j]) < radius {
+                let (a, b) = (root(&mut group_of, i), root(&mut group_of, j));
+                group_of[a] = b;
+            }
+        }
+    }
+    let mut groups: Vec<(usize, Vec<Vec2>)> = vec![];
+    for (i, &pos) in positions.iter().enumerate() {
+        let group = root(&mut group_of, i);
+        match groups.iter_mut().find(|(g, _)| *g == group) {
+            Some((_, members)) => members.push(pos),
+            None => groups.push((group, vec![pos])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, members)| {
+            let center = members.iter().sum::<Vec2>() / members.len() as f32;
+            let spread = members
+                .iter()
+                .map(|pos| pos.distance(center))
+                .fold(0.0, f32::max);
+            (center, spread)
+        })
+        .collect()
+}
+
+fn polyline(
+    painter: &mut ShapePainter,
+    points: impl Iterator<Item = Vec2>,
+    to_screen: &impl Fn(Vec2) -> Vec3,
+) {
+    let mut previous = None;
+    for point in points.map(to_screen) {
+        if let Some(previous) = previous {
+            painter.line(previous, point);
+        }
+        previous = Some(point);
+    }
+}
+
+pub fn draw_map(
+    mut painter: ShapePainter,
+    mut view: ResMut<MapView>,
+    window: Query<&Window, With<PrimaryWindow>>,
+    hierarchy: Option<Res<OrbitHierarchy>>,
+    player: Query<(&Transform, &ComputedTrajectory), With<PlayerMarker>>,
+    alien_ships: Query<(&Transform, &ComputedTrajectory), With<AlienShipMarker>>,
+    celestial_bodies: Query<(Entity, &Transform, &Collider), With<CelestialBodyMarker>>,
+) {
+    let (Ok(window), Ok((pt, traj))) = (window.get_single(), player.get_single()) else {
+        return;
+    };
+    let screen = Vec2::new(window.width(), window.height());
+    let player_pos = pt.translation.xy();
+    if view.fit {
+        let (min, max) = celestial_bodies
+            .iter()
+            .map(|(_, t, _)| t.translation.xy())
+            .chain(std::iter::once(player_pos))
+            .fold((player_pos, player_pos), |(min, max), pos| {
+                (min.min(pos), max.max(pos))
+            });
+        view.pan = (min + max) / 2.0 - player_pos;
+        view.zoom = (MAP_FIT_MARGIN * screen / (max - min).max(Vec2::ONE))
+            .min_element()
+            .clamp(MAP_MIN_ZOOM, MAP_MAX_ZOOM);
+        view.fit = false;
+    }
+    let center = player_pos + view.pan;
+    let zoom = view.zoom;
+    let to_screen = |pos: Vec2| ((pos - center) * zoom).extend(1.0);
+
+    painter.reset();
+    painter.render_layers = Some(RenderLayers::layer(UI_LAYER));
+    painter.set_translation(Vec3::new(0.0, 0.0, 0.5));
+    painter.color = Color::rgba(0.02, 0.02, 0.05, MAP_BACKGROUND_ALPHA);
+    painter.rect(screen);
+
+    painter.set_translation(Vec3::ZERO);
+    painter.thickness = 1.0;
+
+    // Orbits
+    if let Some(hierarchy) = hierarchy {
+        painter.color = Color::rgba(0.6, 0.6, 0.8, MAP_ORBITS_ALPHA);
+        for (parent, orbit) in hierarchy.orbits() {
+            polyline(
+                &mut painter,
+                orbit
+                    .path(MAP_ORBIT_SEGMENTS)
+                    .into_iter()
+                    .map(|offset| parent + offset),
+                &to_screen,
+            );
+        }
+    }
+
+    // Enemies, their courses, and the waves they fly in
+    painter.color = Color::rgba(1.0, 0.2, 0.2, MAP_ENEMY_COURSE_ALPHA);
+    for (t, enemy_traj) in alien_ships.iter() {
+        polyline(
+            &mut painter,
+            std::iter::once(t.translation.xy())
+                .chain(enemy_traj.path.iter().map(|&(point, _)| point)),
+            &to_screen,
+        );
+    }
+    let enemies: Vec<Vec2> = alien_ships
+        .iter()
+        .map(|(t, _)| t.translation.xy())
+        .collect();
+    painter.color = Color::rgba(1.0, 0.2, 0.2, 0.9);
+    painter.hollow = true;
+    for (cluster_center, spread) in clusters(&enemies, MAP_CLUSTER_RADIUS) {
+        painter.set_translation(to_screen(cluster_center));
+        painter.circle((spread * zoom).max(MAP_MIN_BODY_SIZE) + MAP_MIN_BODY_SIZE);
+    }
+    painter.hollow = false;
+    for &pos in enemies.iter() {
+        painter.set_translation(to_screen(pos));
+        painter.circle(1.5);
+    }
+
+    // Bodies
+    for (entity, t, collider) in celestial_bodies.iter() {
+        painter.color = if traj.impact.is_some_and(|impact| impact.body == entity) {
+            Color::rgba(1.0, 0.0, 0.0, 1.0)
+        } else {
+            Color::rgba(0.8, 0.8, 0.9, 1.0)
+        };
+        painter.set_translation(to_screen(t.translation.xy()));
+        painter.circle((scaled_radius(collider, t) * zoom).max(MAP_MIN_BODY_SIZE));
+    }
+
+    // The player's courses
+    painter.set_translation(Vec3::ZERO);
+    painter.color = if traj.impact.is_some() {
+        Color::rgba(1.0, 0.3, 0.0, 0.8)
+    } else {
+        Color::rgba(0.0, 1.0, 0.3, 0.8)
+    };
+    polyline(
+        &mut painter,
+        std::iter::once(player_pos).chain(traj.path.iter().map(|&(point, _)| point)),
+        &to_screen,
+    );
+    if let Some(burn) = &traj.burn {
+        painter.color = Color::rgba(0.2, 0.7, 1.0, 0.8);
+        polyline(
+            &mut painter,
+            std::iter::once(player_pos).chain(burn.path.iter().map(|&(point, _)| point)),
+            &to_screen,
+        );
+    }
+    if let Some(maneuvers) = &traj.maneuvers {
+        painter.color = Color::rgba(1.0, 0.3, 1.0, 0.8);
+        polyline(
+            &mut painter,
+            maneuvers.path.iter().map(|&(point, _)| point),
+            &to_screen,
+        );
+        for node in maneuvers.nodes.iter() {
+            painter.set_translation(to_screen(node.pos));
+            painter.rect(Vec2::splat(6.0));
+        }
+    }
+
+    painter.color = Color::rgba(0.0, 1.0, 0.5, 1.0);
+    painter.set_translation(to_screen(player_pos));
+    painter.set_rotation(pt.rotation);
+    painter.rect(Vec2::new(4.0, 10.0));
+    painter.set_rotation(Quat::default());
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::math::Vec2;
+
+    use super::clusters;
+
+    #[test]
+    fn nearby_ships_are_clustered() {
+        let positions = [
+            Vec2::new(0.0, 0.0),
+            Vec2::new(100.0, 0.0),
+            Vec2::new(200.0, 0.0), // only close to the previous one
+            Vec2::new(10000.0, 0.0),
+        ];
+        let mut found = clusters(&positions, 150.0);
+        found.sort_by(|a, b| a.0.x.total_cmp(&b.0.x));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0], (Vec2::new(100.0, 0.0), 100.0));
+        assert_eq!(found[1], (Vec2::new(10000.0, 0.0), 0.0));
+        assert!(clusters(&[], 150.0).is_empty());
+    }
+}
@@ -12,6 +12,8 @@ pub struct GameSettings {
     pub difficulty: Difficulty,
     pub entities_quantity: EntitiesQuantity,
     pub time_of_death: f32,
+    // `None` plays the hand-made star system, otherwise one generated from the seed.
+    pub system_seed: Option<u64>,
 }
 
 #[derive(Component, Default, EnumIter, Clone, Copy, PartialEq)]
@@ -34,6 +36,17 @@ impl Difficulty {
             Difficulty::Impossible => "Impossible",
         }
     }
+
+    // How much of their lead enemies put into aiming, from shooting where the player is to where they'll be.
+    pub fn aim_accuracy(&self) -> f32 {
+        match self {
+            Difficulty::GodMode => 0.0,
+            Difficulty::Easy => 0.25,
+            Difficulty::Normal => 0.6,
+            Difficulty::Hard => 0.85,
+            Difficulty::Impossible => 1.0,
+        }
+    }
 }
 
 #[derive(Component, Default, EnumIter, Clone, Copy, PartialEq)]
@@ -54,6 +67,30 @@ impl EntitiesQuantity {
     }
 }
 
+#[derive(Component, EnumIter, Clone, Copy, PartialEq)]
+pub enum StarSystemKind {
+    Classic,
+    Procedural,
+}
+
+impl StarSystemKind {
+    fn as_str(&self) -> &'static str {
+        match self {
+            StarSystemKind::Classic => "Classic",
+            StarSystemKind::Procedural => "Procedural",
+        }
+    }
+}
+
+// The seed being edited in the menu, kept while the classic system is selected.
+#[derive(Resource)]
+struct SeedInput(u64);
+
+const MAX_SEED_DIGITS: usize = 10;
+
+#[derive(Component)]
+struct SeedText;
+
 const PRIMARY_COLOR: Color = Color::rgb(0.95, 0.95, 0.95);
 const SECONDARY_COLOR: Color = Color::rgb(0.30, 0.30, 0.30);
 
@@ -65,12 +102,14 @@ pub struct CreditsButton;
 
 pub fn setup(app: &mut App) {
     app.insert_resource(GameSettings::default());
+    app.insert_resource(SeedInput(rand::random::<u32>() as u64));
 
     app.add_systems(OnEnter(AppState::Menu), setup_menu);
     app.add_systems(OnExit(AppState::Menu), cleanup_menu);
     app.add_systems(
         Update,
-        (update_menu, play_on_press_space).run_if(in_state(AppState::Menu)),
+        (update_menu, update_star_system_menu, play_on_press_space)
+            .run_if(in_state(AppState::Menu)),
     );
 }
 
@@ -288,6 +327,94 @@ fn setup_menu(mut commands: Commands, asset_server: Res<AssetServer>) {
 
     commands.entity(menu).add_child(entities_quantity_menu);
 
+    let star_system_title = commands
+        .spawn(TextBundle::from_section(
+            "Star System",
+            TextStyle {
+                font_size: 40.0,
+                font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                color: PRIMARY_COLOR,
+            },
+        ))
+        .id();
+
+    commands.entity(menu).add_child(star_system_title);
+
+    let star_system_menu = commands
+        .spawn((NodeBundle {
+            style: Style {
+                justify_content: JustifyContent::Center,
+                align_items: AlignItems::Center,
+                ..default()
+            },
+            ..default()
+        },))
+        .id();
+
+    for kind in StarSystemKind::iter() {
+        let kind_button = commands
+            .spawn((
+                ButtonBundle {
+                    style: Style {
+                        margin: UiRect {
+                            left: Val::Px(10.),
+                            right: Val::Px(10.),
+                            ..Default::default()
+                        },
+                        padding: UiRect {
+                            left: Val::Px(10.),
+                            right: Val::Px(10.),
+                            top: Val::Px(10.),
+                            bottom: Val::Px(10.),
+                        },
+                        ..default()
+                    },
+                    background_color: Color::NONE.into(),
+                    ..default()
+                },
+                kind,
+            ))
+            .with_children(|parent| {
+                parent.spawn(TextBundle::from_section(
+                    kind.as_str(),
+                    TextStyle {
+                        font_size: 40.0,
+                        font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                        color: PRIMARY_COLOR,
+                    },
+                ));
+            })
+            .id();
+
+        commands.entity(star_system_menu).add_child(kind_button);
+    }
+
+    commands.entity(menu).add_child(star_system_menu);
+
+    // Typing digits edits the seed, [R] draws a new one.
+    let seed_text = commands
+        .spawn((
+            TextBundle::from_section(
+                "",
+                TextStyle {
+                    font_size: 24.0,
+                    font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                    color: PRIMARY_COLOR,
+                },
+            )
+            .with_style(Style {
+                margin: UiRect {
+                    bottom: Val::Px(30.),
+                    ..Default::default()
+                },
+                ..default()
+            }),
+            SeedText,
+        ))
+        .id();
+
+    commands.entity(menu).add_child(seed_text);
+
     let play_button = commands
         .spawn((
             ButtonBundle {
@@ -505,6 +632,68 @@ fn update_menu(
     }
 }
 
+fn update_star_system_menu(
+    mut settings: ResMut<GameSettings>,
+    mut seed_input: ResMut<SeedInput>,
+    mut characters: EventReader<ReceivedCharacter>,
+    keys: Res<Input<KeyCode>>,
+    kind_buttons: Query<(&Interaction, &StarSystemKind, &Children), With<Button>>,
+    mut seed_text: Query<&mut Text, With<SeedText>>,
+    mut text_query: Query<&mut Text, Without<SeedText>>,
+) {
+    for (interaction, kind, _) in kind_buttons.iter() {
+        if *interaction == Interaction::Pressed {
+            settings.system_seed = match kind {
+                StarSystemKind::Classic => None,
+                StarSystemKind::Procedural => Some(seed_input.0),
+            };
+        }
+    }
+    let selected = match settings.system_seed {
+        Some(_) => StarSystemKind::Procedural,
+        None => StarSystemKind::Classic,
+    };
+    for (_, kind, children) in kind_buttons.iter() {
+        if let Ok(mut text) = text_query.get_mut(children[0]) {
+            text.sections[0].style.color = match *kind == selected {
+                true => PRIMARY_COLOR,
+                false => SECONDARY_COLOR,
+            };
+        }
+    }
+
+    let Ok(mut text) = seed_text.get_single_mut() else {
+        return;
+    };
+    if selected == StarSystemKind::Classic {
+        characters.clear();
+        text.sections[0].value = String::new();
+        return;
+    }
+    let mut digits = seed_input.0.to_string();
+    for c in characters.read().map(|event| event.char) {
+        if c.is_ascii_digit() && digits.len() < MAX_SEED_DIGITS {
+            // Typing over a zero seed replaces it.
+            if digits == "0" {
+                digits.clear();
+            }
+            digits.push(c);
+        }
+    }
+    if keys.just_pressed(KeyCode::Back) {
+        digits.pop();
+    }
+    if keys.just_pressed(KeyCode::R) {
+        digits = rand::random::<u32>().to_string();
+    }
+    let seed = digits.parse().unwrap_or(0);
+    if seed != seed_input.0 {
+        seed_input.0 = seed;
+        settings.system_seed = Some(seed);
+    }
+    text.sections[0].value = format!("Seed {}   [R] new seed", seed);
+}
+
 fn play_on_press_space(mut next_state: ResMut<NextState<AppState>>, keys: Res<Input<KeyCode>>) {
     if keys.pressed(KeyCode::Space) {
         next_state.set(AppState::Game);
@@ -4,7 +4,10 @@ mod credits_screen;
 mod death_screen;
 mod diagnostics;
 mod healthbar;
+mod maneuver_hud;
+pub mod map;
 mod menu;
+mod orbit_hud;
 pub mod radar;
 mod score;
 
@@ -18,5 +21,8 @@ pub fn setup(app: &mut App) {
     credits_screen::setup(app);
     radar::setup(app);
     healthbar::setup(app);
+    maneuver_hud::setup(app);
+    map::setup(app);
+    orbit_hud::setup(app);
     diagnostics::setup(app);
 }
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+
+use crate::{landing::Landed, orbital_elements::PlayerOrbit, player::PlayerMarker, AppState};
+
+#[derive(Component)]
+pub struct OrbitHudText;
+
+pub fn setup(app: &mut App) {
+    app.add_systems(OnEnter(AppState::Game), setup_orbit_hud);
+    app.add_systems(OnExit(AppState::Game), cleanup_orbit_hud);
+    app.add_systems(Update, update_orbit_hud.run_if(in_state(AppState::Game)));
+}
+
+fn setup_orbit_hud(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                font_size: 20.0,
+                color: Color::rgb(0.5, 1.0, 0.8),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            right: Val::Px(20.0),
+            bottom: Val::Px(60.0),
+            ..default()
+        })
+        .with_text_alignment(TextAlignment::Right),
+        OrbitHudText,
+    ));
+}
+
+fn cleanup_orbit_hud(mut commands: Commands, text_query: Query<Entity, With<OrbitHudText>>) {
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_orbit_hud(
+    orbit: Res<PlayerOrbit>,
+    names: Query<&Name>,
+    landed: Query<&Landed, With<PlayerMarker>>,
+    mut text_query: Query<&mut Text, With<OrbitHudText>>,
+) {
+    if !orbit.is_changed() {
+        return;
+    }
+    let Ok(mut text) = text_query.get_single_mut() else {
+        return;
+    };
+    let body_name = |body: Entity| {
+        names.get(body).map_or("UNKNOWN BODY".to_string(), |name| {
+            name.as_str().to_uppercase()
+        })
+    };
+    if let Ok(landed) = landed.get_single() {
+        text.sections[0].value = format!("LANDED ON {}\nREPAIRING", body_name(landed.body));
+        return;
+    }
+    let (Some(body), Some(elements)) = (orbit.body, orbit.elements) else {
+        text.sections[0].value = String::new();
+        return;
+    };
+    let name = body_name(body);
+    // Altitudes are measured from the body's surface.
+    let altitude = |d: f32| d - orbit.body_radius;
+    let mut lines = vec![
+        format!("ORBITING {}", name),
+        format!("PERIAPSIS {:.0}", altitude(elements.periapsis)),
+    ];
+    if elements.escaping {
+        lines.push("ESCAPE TRAJECTORY".to_string());
+    } else {
+        lines.push(match elements.apoapsis {
+            Some(apoapsis) => format!("APOAPSIS {:.0}", altitude(apoapsis)),
+            None => "APOAPSIS --".to_string(),
+        });
+        lines.push(match elements.period {
+            Some(period) => format!("PERIOD {:.1}s", period),
+            None => "PERIOD --".to_string(),
+        });
+    }
+    if elements.crashing {
+        lines.push("PERIAPSIS BELOW SURFACE".to_string());
+    }
+    text.sections[0].value = lines.join("\n");
+}
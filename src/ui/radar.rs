@@ -25,6 +25,9 @@ const RADAR_ENTITIES_ALPHA: f32 = 0.9;
 const RADAR_CELESTIAL_BODIES_ALPHA: f32 = 0.7;
 const RADAR_CIRCLES_ALPHA: f32 = 0.6;
 const RADAR_PLANNED_COURSE_ALPHA: f32 = 0.4;
+const RADAR_BURN_COURSE_ALPHA: f32 = 0.5;
+const RADAR_MANEUVER_COURSE_ALPHA: f32 = 0.6;
+const IMPACT_WARNING_TIME_S: f32 = 10.0;
 
 #[derive(Resource)]
 pub struct RadarShipsColorGradient(pub colorgrad::Gradient);
@@ -32,8 +35,19 @@ pub struct RadarShipsColorGradient(pub colorgrad::Gradient);
 #[derive(Resource)]
 pub struct CoursePlanningColorGradient(pub colorgrad::Gradient);
 
+#[derive(Component)]
+pub struct ImpactWarningText;
+
 pub fn setup(app: &mut App) {
-    app.add_systems(OnEnter(AppState::Game), setup_radar_hud);
+    app.add_systems(
+        OnEnter(AppState::Game),
+        (setup_radar_hud, setup_impact_warning),
+    );
+    app.add_systems(OnExit(AppState::Game), cleanup_impact_warning);
+    app.add_systems(
+        Update,
+        update_impact_warning.run_if(in_state(AppState::Game)),
+    );
 
     // app.add_systems(
     //     Update,
@@ -66,13 +80,62 @@ pub fn setup_radar_hud(mut commands: Commands) {
     ));
 }
 
+fn setup_impact_warning(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands.spawn((
+        TextBundle::from_section(
+            "",
+            TextStyle {
+                font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                font_size: 30.0,
+                color: Color::rgb(1.0, 0.2, 0.2),
+            },
+        )
+        .with_style(Style {
+            position_type: PositionType::Absolute,
+            bottom: Val::Px(60.0),
+            width: Val::Percent(100.),
+            justify_content: JustifyContent::Center,
+            ..default()
+        })
+        .with_text_alignment(TextAlignment::Center),
+        ImpactWarningText,
+    ));
+}
+
+fn cleanup_impact_warning(
+    mut commands: Commands,
+    text_query: Query<Entity, With<ImpactWarningText>>,
+) {
+    for entity in text_query.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+fn update_impact_warning(
+    time: Res<Time>,
+    player: Query<&ComputedTrajectory, With<PlayerMarker>>,
+    mut text_query: Query<&mut Text, With<ImpactWarningText>>,
+) {
+    if let Ok(mut text) = text_query.get_single_mut() {
+        let time_to_impact = player
+            .get_single()
+            .ok()
+            .and_then(|traj| traj.time_to_impact(time.elapsed_seconds()))
+            .filter(|t| (0.0..IMPACT_WARNING_TIME_S).contains(t));
+        text.sections[0].value = match time_to_impact {
+            Some(t) => format!("IMPACT IN {:.1}s", t),
+            None => String::new(),
+        };
+    }
+}
+
 pub fn draw_radar_hud(
     mut painter: ShapePainter,
     ship_color_gradient: Res<RadarShipsColorGradient>,
     course_color_gradient: Res<CoursePlanningColorGradient>,
     player: Query<(&Transform, &Velocity, &ComputedTrajectory), With<PlayerMarker>>,
     alien_ships: Query<(&Transform, &Velocity), With<AlienShipMarker>>,
-    celestial_bodies: Query<(&Transform, &Collider), With<CelestialBodyMarker>>,
+    celestial_bodies: Query<(Entity, &Transform, &Collider), With<CelestialBodyMarker>>,
 ) {
     if let Ok((pt, pv, traj)) = player.get_single() {
         // Draw Radar circles
@@ -139,7 +202,7 @@ pub fn draw_radar_hud(
             painter.rect(Vec2::splat(size));
         }
 
-        for (at, collider) in celestial_bodies.iter() {
+        for (entity, at, collider) in celestial_bodies.iter() {
             let dp = at.translation.xy() - pt.translation.xy();
             let dv = -pv.linvel; // Todo if needed: take body velocity into account
 
@@ -156,7 +219,12 @@ pub fn draw_radar_hud(
                     - ((body_closing_speed + RADAR_COLOR_MAX_SPEED)
                         / (RADAR_COLOR_MAX_SPEED * 2.0))
                         .clamp(0.0, 1.0);
-                let ship_radar_color = ship_color_gradient.0.at(speed_color_interp.into());
+                let ship_radar_color = if traj.impact.is_some_and(|impact| impact.body == entity) {
+                    // We're going to crash into this one.
+                    colorgrad::Color::new(1.0, 0.0, 0.0, 1.0)
+                } else {
+                    ship_color_gradient.0.at(speed_color_interp.into())
+                };
 
                 painter.set_translation(Vec3 {
                     x: theta.cos() * radar_r,
@@ -203,7 +271,7 @@ pub fn draw_radar_hud(
         for (i, &(point, d)) in traj.path.iter().enumerate() {
             let p = vec_to_radar(point - pt.translation.xy());
             if p.length() < RADAR_HUD_OUTER_RADIUS - 0.1 {
-                let (color, alpha) = if traj.closest_flyby < 32.0 {
+                let (color, alpha) = if traj.impact.is_some() {
                     (
                         course_color_gradient
                             .0
@@ -224,6 +292,55 @@ pub fn draw_radar_hud(
                 painter.circle(0.5);
             }
         }
+
+        // Draw the course we'd follow if we kept burning
+        if let Some(burn) = &traj.burn {
+            let burn_end = (burn.duration / traj.step_dt) as usize;
+            for (i, &(point, _)) in burn.path.iter().enumerate() {
+                let p = vec_to_radar(point - pt.translation.xy());
+                if p.length() < RADAR_HUD_OUTER_RADIUS - 0.1 {
+                    painter.set_translation(p.extend(0.0));
+                    painter.hollow = true;
+                    painter.color = if burn.impact.is_some() {
+                        Color::rgba(1.0, 0.2, 0.0, RADAR_BURN_COURSE_ALPHA)
+                    } else {
+                        Color::rgba(0.2, 0.7, 1.0, RADAR_BURN_COURSE_ALPHA)
+                    };
+                    painter.thickness = ((i as f32 / max_segments).powf(3.0) * 15.0).max(0.5);
+                    // Mark where the engine would be cut off
+                    painter.circle(if i == burn_end { 3.0 } else { 0.5 });
+                }
+            }
+        }
+
+        // Draw the course through the maneuver nodes
+        if let Some(maneuvers) = &traj.maneuvers {
+            painter.thickness = 1.0;
+            painter.hollow = true;
+            painter.color = if maneuvers.impact.is_some() {
+                Color::rgba(1.0, 0.2, 0.0, RADAR_MANEUVER_COURSE_ALPHA)
+            } else {
+                Color::rgba(1.0, 0.3, 1.0, RADAR_MANEUVER_COURSE_ALPHA)
+            };
+            for &(point, _) in maneuvers.path.iter() {
+                let p = vec_to_radar(point - pt.translation.xy());
+                if p.length() < RADAR_HUD_OUTER_RADIUS - 0.1 {
+                    painter.set_translation(p.extend(0.0));
+                    painter.circle(0.5);
+                }
+            }
+            painter.color = Color::rgba(1.0, 0.3, 1.0, 1.0);
+            for node in maneuvers.nodes.iter() {
+                let p = vec_to_radar(node.pos - pt.translation.xy());
+                painter.set_translation(p.extend(0.0));
+                painter.set_rotation(Quat::from_axis_angle(
+                    Vec3::Z,
+                    node.delta_v.y.atan2(node.delta_v.x),
+                ));
+                painter.rect(Vec2::splat(6.0));
+            }
+            painter.set_rotation(Quat::default());
+        }
     }
 }
 
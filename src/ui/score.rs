@@ -1,27 +1,94 @@
-use bevy::{prelude::*, utils::Instant};
+use std::collections::VecDeque;
+
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
+    alien_waves::AlienWave,
+    death::KillKind,
+    gameplay_events::ShipDestroyed,
+    scoring::{self, StyleAwarded, StyleScoring},
     ui::{Difficulty, GameSettings},
     AppState,
 };
 
+const FEED_ENTRY_S: f32 = 3.0;
+const FEED_MAX_ENTRIES: usize = 6;
+
 #[derive(Resource)]
 pub struct Score {
+    pub enemies_killed: u32, // only the ones the player gets credit for
+    pub kills: HashMap<KillKind, u32>,
+    pub kill_points: i32, // see `scoring`
+    pub style_points: i32,
+    pub game_time: f32, // stops when the game's clock does, unlike the wall clock
+}
+
+// The breakdown of a finished run, for the death screen.
+#[derive(Resource, Default)]
+pub struct RunSummary {
+    pub duration: f32,
+    pub waves: u32,
     pub enemies_killed: u32,
-    pub time_game_start: Instant,
+    pub kills: HashMap<KillKind, u32>,
+    pub kill_points: i32,
+    pub style_points: i32,
+    pub best_combo: f32,
+    pub time_penalty: i32,
+    pub difficulty_multiplier: i32,
+    pub final_score: i32,
 }
 
+impl RunSummary {
+    pub fn new(score: &Score, settings: &GameSettings, best_combo: f32, waves: u32) -> Self {
+        Self {
+            duration: score.game_time,
+            waves,
+            enemies_killed: score.enemies_killed,
+            kills: score.kills.clone(),
+            kill_points: score.kill_points,
+            style_points: score.style_points,
+            best_combo,
+            time_penalty: time_penalty(score.game_time, settings),
+            difficulty_multiplier: score_multiplier(settings),
+            final_score: compute_score(score, settings),
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct ScoreHud;
+
 #[derive(Component)]
 pub struct ScoreHudText;
 
+#[derive(Component)]
+pub struct ScoreFeedText;
+
+// The last points awarded, shown under the score for a little while.
+#[derive(Resource, Default)]
+struct ScoreFeed(VecDeque<(String, f32)>);
+
 pub fn setup(app: &mut App) {
     app.insert_resource(Score {
         enemies_killed: 0,
-        time_game_start: Instant::now(),
+        kills: HashMap::new(),
+        kill_points: 0,
+        style_points: 0,
+        game_time: 0.0,
     });
+    app.init_resource::<RunSummary>();
+    app.init_resource::<ScoreFeed>();
     app.add_systems(OnEnter(AppState::Game), setup_score_hud);
-    app.add_systems(OnExit(AppState::Game), cleanup_score_hud);
-    app.add_systems(Update, update_score_hud.run_if(in_state(AppState::Game)));
+    app.add_systems(
+        OnExit(AppState::Game),
+        (record_run_summary, cleanup_score_hud),
+    );
+    app.add_systems(
+        Update,
+        (count_kills, update_score_hud, update_score_feed)
+            .chain()
+            .run_if(in_state(AppState::Game)),
+    );
 }
 
 fn setup_score_hud(
@@ -30,18 +97,27 @@ fn setup_score_hud(
     mut score: ResMut<Score>,
 ) {
     score.enemies_killed = 0;
-    score.time_game_start = Instant::now();
+    score.kills.clear();
+    score.kill_points = 0;
+    score.style_points = 0;
+    score.game_time = 0.0;
+    commands.insert_resource(ScoreFeed::default());
 
     commands
-        .spawn(NodeBundle {
-            style: Style {
-                // center button
-                width: Val::Percent(100.),
-                justify_content: JustifyContent::Center,
+        .spawn((
+            NodeBundle {
+                style: Style {
+                    // center button
+                    width: Val::Percent(100.),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    flex_direction: FlexDirection::Column,
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+            ScoreHud,
+        ))
         .with_children(|parent| {
             parent.spawn((
                 TextBundle::from_section(
@@ -54,10 +130,22 @@ fn setup_score_hud(
                 ),
                 ScoreHudText,
             ));
+            parent.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font: asset_server.load("fusion-pixel-12px-proportional-latin.ttf"),
+                        font_size: 20.0,
+                        color: Color::rgb(1.0, 0.85, 0.3),
+                    },
+                )
+                .with_text_alignment(TextAlignment::Center),
+                ScoreFeedText,
+            ));
         });
 }
 
-fn cleanup_score_hud(mut commands: Commands, text_query: Query<Entity, With<ScoreHudText>>) {
+fn cleanup_score_hud(mut commands: Commands, text_query: Query<Entity, With<ScoreHud>>) {
     for entity in text_query.iter() {
         commands.entity(entity).despawn_recursive();
     }
@@ -81,14 +169,36 @@ pub fn time_power(settings: &GameSettings) -> f32 {
     }
 }
 
+pub fn time_penalty(game_time: f32, settings: &GameSettings) -> i32 {
+    game_time.powf(time_power(settings)).round() as i32
+}
+
 pub fn compute_score(score: &Score, settings: &GameSettings) -> i32 {
     let score_multiplier = score_multiplier(settings);
-    let enemies_killed = (score.enemies_killed * 10) as i32;
-    let game_duration = Instant::now()
-        .duration_since(score.time_game_start)
-        .as_secs_f32();
+    let points = score.kill_points + score.style_points;
+
+    (points - time_penalty(score.game_time, settings)) * score_multiplier
+}
 
-    (enemies_killed - game_duration.powf(time_power(settings)).round() as i32) * score_multiplier
+fn record_run_summary(
+    mut commands: Commands,
+    score: Res<Score>,
+    settings: Res<GameSettings>,
+    style: Res<StyleScoring>,
+    wave: Option<Res<AlienWave>>,
+) {
+    // The wave counter points at the next wave to start.
+    let waves = wave.map_or(0, |wave| wave.current_wave.saturating_sub(1));
+    commands.insert_resource(RunSummary::new(&score, &settings, style.best_combo, waves));
+}
+
+fn count_kills(mut destroyed: EventReader<ShipDestroyed>, mut score: ResMut<Score>) {
+    for kill in destroyed.read().filter_map(|event| event.kill) {
+        *score.kills.entry(kill).or_default() += 1;
+        if kill.credited() {
+            score.enemies_killed += 1;
+        }
+    }
 }
 
 fn update_score_hud(
@@ -100,3 +210,77 @@ fn update_score_hud(
         text.sections[0].value = format!("Score: {}", compute_score(&score, &settings));
     }
 }
+
+fn style_label(style: scoring::Style) -> String {
+    match style {
+        scoring::Style::Kill(kind) => kind.label().to_uppercase(),
+        scoring::Style::MultiKill(2) => "DOUBLE KILL".to_string(),
+        scoring::Style::MultiKill(3) => "TRIPLE KILL".to_string(),
+        scoring::Style::MultiKill(n) => format!("MULTI KILL x{}", n),
+        scoring::Style::Streak(n) => format!("{} KILL STREAK", n),
+        scoring::Style::Flyby => "LOW FLYBY".to_string(),
+        scoring::Style::NearMiss => "NEAR MISS".to_string(),
+        scoring::Style::GravityAssist => "GRAVITY ASSIST".to_string(),
+    }
+}
+
+fn update_score_feed(
+    time: Res<Time>,
+    mut feed: ResMut<ScoreFeed>,
+    style: Res<StyleScoring>,
+    mut awards: EventReader<StyleAwarded>,
+    mut text_query: Query<&mut Text, With<ScoreFeedText>>,
+) {
+    let now = time.elapsed_seconds();
+    for award in awards.read() {
+        let mut line = format!("+{} {}", award.points, style_label(award.style));
+        if award.multiplier > 1.0 {
+            line += &format!(" x{:.2}", award.multiplier);
+        }
+        feed.0.push_front((line, now));
+    }
+    feed.0.truncate(FEED_MAX_ENTRIES);
+    feed.0.retain(|&(_, at)| now - at < FEED_ENTRY_S);
+    if let Ok(mut text) = text_query.get_single_mut() {
+        let combo = style.combo.multiplier(now);
+        let header = (combo > 1.0).then(|| format!("COMBO x{:.2}", combo));
+        text.sections[0].value = header
+            .into_iter()
+            .chain(feed.0.iter().map(|(line, _)| line.clone()))
+            .collect::<Vec<String>>()
+            .join("\n");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::utils::HashMap;
+
+    use super::{compute_score, time_penalty, RunSummary, Score};
+    use crate::ui::{Difficulty, GameSettings};
+
+    #[test]
+    fn score_only_runs_with_the_game_clock() {
+        let settings = GameSettings {
+            difficulty: Difficulty::Hard,
+            ..Default::default()
+        };
+        let mut score = Score {
+            enemies_killed: 3,
+            kills: HashMap::new(),
+            kill_points: 30,
+            style_points: 45,
+            game_time: 20.0,
+        };
+        let before = compute_score(&score, &settings);
+        score.game_time = 30.0;
+        assert!(compute_score(&score, &settings) < before);
+
+        let summary = RunSummary::new(&score, &settings, 2.5, 4);
+        assert_eq!(summary.time_penalty, time_penalty(30.0, &settings));
+        assert_eq!(
+            summary.final_score,
+            (30 + 45 - summary.time_penalty) * summary.difficulty_multiplier
+        );
+    }
+}